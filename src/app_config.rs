@@ -1,76 +1,877 @@
-use serde::{Deserialize, Serialize};
+use crate::sources::is_tsv;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
+/// Environment variable naming a config file to use instead of the default.
+pub const CONFIG_ENV_VAR: &str = "CSV_LINK_EXTRACTOR_CONFIG";
+
+/// Config file given with `--config`, which wins over [`CONFIG_ENV_VAR`].
+static CONFIG_OVERRIDE: OnceCell<PathBuf> = OnceCell::new();
+
+/// Profile given with `--profile`, switched to whenever the config is loaded.
+static PROFILE_OVERRIDE: OnceCell<String> = OnceCell::new();
+
+/// Name of the profile the settings belong to before any other is saved.
+pub const DEFAULT_PROFILE: &str = "Default";
+
+/// Config file format, chosen by the file's extension: `.toml`, `.yaml` or
+/// `.yml`, and JSON for anything else.
+#[derive(Clone, Copy)]
+enum ConfigFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl ConfigFormat {
+    fn of(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("toml") => ConfigFormat::Toml,
+            Some("yaml" | "yml") => ConfigFormat::Yaml,
+            _ => ConfigFormat::Json,
+        }
+    }
+
+    fn parse(self, contents: &str) -> Result<AppConfig, String> {
+        match self {
+            ConfigFormat::Json => serde_json::from_str(contents).map_err(|e| e.to_string()),
+            ConfigFormat::Toml => toml::from_str(contents).map_err(|e| e.to_string()),
+            ConfigFormat::Yaml => serde_yaml::from_str(contents).map_err(|e| e.to_string()),
+        }
+    }
+
+    fn serialize(self, config: &AppConfig) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(match self {
+            ConfigFormat::Json => serde_json::to_string_pretty(config)?,
+            ConfigFormat::Toml => toml::to_string_pretty(config)?,
+            ConfigFormat::Yaml => serde_yaml::to_string(config)?,
+        })
+    }
+}
+
+/// Current config schema version, bumped whenever a change needs more than
+/// new fields taking their defaults; see [`AppConfig::migrate`].
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Saved settings. Fields missing from an older file take their default
+/// values, so adding a field never invalidates existing configs.
 #[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct AppConfig {
+    /// Schema version the file was written with; 0 for files from before
+    /// versioning
+    #[serde(default)]
+    pub version: u32,
     pub directory: String,
     pub output: String,
     pub skip_header: bool,
     pub workers: usize,
+    /// Comma- or newline-separated exclude file paths
     pub exclude_file: String,
+    pub exclude_patterns: String,
     pub continue_on_error: bool,
+    /// Retries after a transient error opening a file, and the first wait in ms
+    pub read_retries: u32,
+    pub read_retry_delay_ms: u64,
     pub master_list_path: String,
+    /// Where charts and reports are written; empty means the default data directory
+    pub statistics_dir: String,
     pub sample_file_path: String,
-    pub selected_header: String,
+    /// Columns to extract URLs from. Older configs stored a single
+    /// `selected_header` string, which is still accepted.
+    #[serde(alias = "selected_header", deserialize_with = "one_or_many")]
+    pub selected_headers: Vec<String>,
     pub statistics: Statistics,
     pub use_timestamp: bool,
+    pub report_high_volume: bool,
+    pub high_volume_threshold: usize,
+    pub write_excluded: bool,
+    pub group_by_domain: bool,
+    pub write_invalid: bool,
+    pub trim: CsvTrim,
+    pub dedup_regex: String,
+    pub chart_timeout_secs: u64,
+    pub include_first_seen: bool,
+    pub recursive: bool,
+    /// Comma-separated file name globs; blank reads every CSV file
+    pub file_patterns: String,
+    pub extensions: String,
+    pub parallel_records: bool,
+    pub split_cells: bool,
+    pub scan_text: bool,
+    pub check_live: bool,
+    pub timeout_secs: u64,
+    pub delimiter: Delimiter,
+    pub encoding: InputEncoding,
+    pub output_format: OutputFormat,
+    pub line_ending: LineEnding,
+    pub with_source: bool,
+    pub count_occurrences: bool,
+    pub normalize: bool,
+    pub tracking_params: String,
+    pub strip_query: bool,
+    pub strip_fragment: bool,
+    pub include_domains: String,
+    pub exclude_domains: String,
+    pub chart_top_n: usize,
+    /// Domain chart size in pixels
+    pub chart_width: u32,
+    pub chart_height: u32,
+    pub chart_format: ChartFormat,
+    pub max_history_sessions: usize,
+    pub master_list_backups: usize,
+    pub rewrite_rules: Vec<RewriteRule>,
+    /// Comma-separated URL schemes accepted as valid
+    pub schemes: String,
+    /// Column index read from files that lack every selected column
+    pub fallback_column: Option<usize>,
+    /// Unique URLs collected before a run stops early
+    pub limit: Option<usize>,
+    /// Valid URLs outside these character bounds are dropped
+    pub min_url_len: Option<usize>,
+    pub max_url_len: Option<usize>,
+    /// URLs written per host at most
+    pub max_per_domain: Option<usize>,
+    pub sort: SortOrder,
+    pub theme: Theme,
+    pub append: bool,
+    pub dedup_mode: DedupMode,
+    pub prune_days: u64,
+    /// Window inner size and outer position in points, restored at startup
+    pub window_size: [f32; 2],
+    pub window_pos: Option<[f32; 2]>,
+    /// Saved settings by profile name. The fields above are the working copy
+    /// of `active_profile`, stored back here when switching away from it.
+    pub profiles: BTreeMap<String, AppConfig>,
+    pub active_profile: String,
+}
+
+/// A literal find/replace applied to every extracted URL before validation.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct RewriteRule {
+    pub find: String,
+    pub replace: String,
+    pub enabled: bool,
+}
+
+impl RewriteRule {
+    /// Rules shipped with the app, all disabled until the user opts in.
+    pub fn defaults() -> Vec<RewriteRule> {
+        vec![RewriteRule {
+            find: String::from("linkedin.com/job-apply/"),
+            replace: String::from("linkedin.com/jobs/view/"),
+            enabled: false,
+        }]
+    }
+}
+
+/// Whitespace trimming applied by the CSV reader itself.
+///
+/// Trimming headers lets a selected column match even when the export pads
+/// header names; trimming fields does the same for URL cells. URL cells are
+/// still trimmed manually after parsing, so `None`/`Headers` only affect how
+/// other columns and headers are matched.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default, clap::ValueEnum)]
+pub enum CsvTrim {
+    None,
+    Headers,
+    Fields,
+    #[default]
+    All,
+}
+
+impl CsvTrim {
+    pub const ALL: [CsvTrim; 4] = [CsvTrim::None, CsvTrim::Headers, CsvTrim::Fields, CsvTrim::All];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CsvTrim::None => "None",
+            CsvTrim::Headers => "Headers",
+            CsvTrim::Fields => "Fields",
+            CsvTrim::All => "All",
+        }
+    }
+}
+
+impl From<CsvTrim> for csv::Trim {
+    fn from(trim: CsvTrim) -> Self {
+        match trim {
+            CsvTrim::None => csv::Trim::None,
+            CsvTrim::Headers => csv::Trim::Headers,
+            CsvTrim::Fields => csv::Trim::Fields,
+            CsvTrim::All => csv::Trim::All,
+        }
+    }
+}
+
+/// Field delimiter for CSV files. `Auto` reads `.tsv` files as tab-separated
+/// and sniffs the first line of anything else.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default, clap::ValueEnum)]
+pub enum Delimiter {
+    #[default]
+    Auto,
+    Comma,
+    Semicolon,
+    Tab,
+    Pipe,
+}
+
+impl Delimiter {
+    pub const ALL: [Delimiter; 5] = [
+        Delimiter::Auto,
+        Delimiter::Comma,
+        Delimiter::Semicolon,
+        Delimiter::Tab,
+        Delimiter::Pipe,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Delimiter::Auto => "Auto-detect",
+            Delimiter::Comma => "Comma (,)",
+            Delimiter::Semicolon => "Semicolon (;)",
+            Delimiter::Tab => "Tab",
+            Delimiter::Pipe => "Pipe (|)",
+        }
+    }
+
+    /// The delimiter for reading `path`: auto-detection becomes a tab for
+    /// `.tsv` files, anything forced is kept.
+    pub fn for_path(self, path: &Path) -> Delimiter {
+        if self == Delimiter::Auto && is_tsv(path) {
+            Delimiter::Tab
+        } else {
+            self
+        }
+    }
+
+    /// The forced delimiter byte, or `None` when it should be detected.
+    pub fn byte(self) -> Option<u8> {
+        match self {
+            Delimiter::Auto => None,
+            Delimiter::Comma => Some(b','),
+            Delimiter::Semicolon => Some(b';'),
+            Delimiter::Tab => Some(b'\t'),
+            Delimiter::Pipe => Some(b'|'),
+        }
+    }
+}
+
+/// Text encoding of input files. `Auto` honours a BOM (UTF-8 or UTF-16) and
+/// otherwise falls back to Windows-1252 when the data isn't valid UTF-8.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default, clap::ValueEnum)]
+pub enum InputEncoding {
+    #[default]
+    Auto,
+    Utf8,
+    Windows1252,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl InputEncoding {
+    pub const ALL: [InputEncoding; 5] = [
+        InputEncoding::Auto,
+        InputEncoding::Utf8,
+        InputEncoding::Windows1252,
+        InputEncoding::Utf16Le,
+        InputEncoding::Utf16Be,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            InputEncoding::Auto => "Auto-detect",
+            InputEncoding::Utf8 => "UTF-8",
+            InputEncoding::Windows1252 => "Windows-1252",
+            InputEncoding::Utf16Le => "UTF-16 LE",
+            InputEncoding::Utf16Be => "UTF-16 BE",
+        }
+    }
+}
+
+/// Layout of the output file.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// One URL per line
+    #[default]
+    Txt,
+    /// Array of `{url, source_file, domain}` objects
+    Json,
+    /// Header row plus `url,source_file,domain`
+    Csv,
+}
+
+impl OutputFormat {
+    pub const ALL: [OutputFormat; 3] = [OutputFormat::Txt, OutputFormat::Json, OutputFormat::Csv];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            OutputFormat::Txt => "Text (one URL per line)",
+            OutputFormat::Json => "JSON",
+            OutputFormat::Csv => "CSV",
+        }
+    }
+}
+
+/// Image format of the statistics charts.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default, clap::ValueEnum)]
+pub enum ChartFormat {
+    /// Bitmap, viewable anywhere
+    #[default]
+    Png,
+    /// Vector, stays sharp when scaled in documents
+    Svg,
+}
+
+impl ChartFormat {
+    pub const ALL: [ChartFormat; 2] = [ChartFormat::Png, ChartFormat::Svg];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ChartFormat::Png => "PNG",
+            ChartFormat::Svg => "SVG",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ChartFormat::Png => "png",
+            ChartFormat::Svg => "svg",
+        }
+    }
+}
+
+/// Line separator written to the output file.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default, clap::ValueEnum)]
+pub enum LineEnding {
+    /// `\n`, as on Linux and macOS
+    #[default]
+    Lf,
+    /// `\r\n`, for Windows tools that expect it
+    Crlf,
+}
+
+impl LineEnding {
+    pub const ALL: [LineEnding; 2] = [LineEnding::Lf, LineEnding::Crlf];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "LF (Unix)",
+            LineEnding::Crlf => "CRLF (Windows)",
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+        }
+    }
+}
+
+/// How URLs are compared when deduplicating and checking the master list.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default, clap::ValueEnum)]
+pub enum DedupMode {
+    /// Byte-for-byte comparison
+    Exact,
+    /// Scheme and host compared case-insensitively; the path keeps its case
+    #[default]
+    CaseInsensitiveHost,
+    /// Default ports and trailing slashes ignored, and the whole URL compared
+    /// case-insensitively
+    FullyNormalized,
+}
+
+impl DedupMode {
+    pub const ALL: [DedupMode; 3] = [DedupMode::Exact, DedupMode::CaseInsensitiveHost, DedupMode::FullyNormalized];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DedupMode::Exact => "Exact",
+            DedupMode::CaseInsensitiveHost => "Case-insensitive host",
+            DedupMode::FullyNormalized => "Fully normalized",
+        }
+    }
+}
+
+/// Order in which URLs are written to the output file.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default, clap::ValueEnum)]
+pub enum SortOrder {
+    /// Whatever order deduplication produced; fastest, but differs between runs
+    None,
+    /// Alphabetical by the full URL
+    #[default]
+    Alpha,
+    /// Grouped by host, then by path
+    Domain,
+}
+
+impl SortOrder {
+    pub const ALL: [SortOrder; 3] = [SortOrder::None, SortOrder::Alpha, SortOrder::Domain];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortOrder::None => "Unsorted",
+            SortOrder::Alpha => "Alphabetical",
+            SortOrder::Domain => "By domain",
+        }
+    }
+}
+
+/// Colour scheme of the window.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+    /// Follow the operating system's light/dark setting
+    System,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::Dark, Theme::Light, Theme::System];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Theme::Dark => "Dark",
+            Theme::Light => "Light",
+            Theme::System => "Follow system",
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
 pub struct Statistics {
     pub total_files_processed: usize,
     pub total_urls_found: usize,
+    /// Distinct URLs extracted, before exclusions and the master list
+    pub distinct_urls: usize,
+    /// URLs written, i.e. new to the output and master list
     pub unique_urls: usize,
     pub excluded_urls: usize,
+    pub invalid_urls: usize,
     pub duplicate_urls: usize,
+    /// Errors reported while reading the files
+    pub file_errors: usize,
     pub processing_time: f64,
     pub last_run: Option<String>,
+    pub live_urls: Option<usize>,
+    pub dead_urls: Option<usize>,
+}
+
+impl Statistics {
+    /// Renders the run counters as an aligned plain-text table for the CLI.
+    pub fn summary_table(&self) -> String {
+        let rows = [
+            ("Files processed", self.total_files_processed.to_string()),
+            ("URLs found", self.total_urls_found.to_string()),
+            ("Unique URLs", self.distinct_urls.to_string()),
+            ("New URLs", self.unique_urls.to_string()),
+            ("Duplicates", self.duplicate_urls.to_string()),
+            ("Excluded", self.excluded_urls.to_string()),
+            ("Invalid", self.invalid_urls.to_string()),
+            ("Errors", self.file_errors.to_string()),
+            ("Elapsed", format!("{:.2}s", self.processing_time)),
+        ];
+        let label_width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+        let value_width = rows.iter().map(|(_, value)| value.len()).max().unwrap_or(0);
+        let border = format!("+-{}-+-{}-+\n", "-".repeat(label_width), "-".repeat(value_width));
+
+        let mut table = border.clone();
+        for (label, value) in &rows {
+            table.push_str(&format!(
+                "| {:<lw$} | {:>vw$} |\n",
+                label,
+                value,
+                lw = label_width,
+                vw = value_width
+            ));
+        }
+        table.push_str(&border);
+        table
+    }
 }
 
 impl AppConfig {
+    /// Loads the config file, switched to the `--profile` profile if one was
+    /// given.
     pub fn load() -> Self {
+        Self::load_with_warning().0
+    }
+
+    /// Like [`load`](Self::load), also returning a warning for the user when
+    /// a config file exists but couldn't be used and defaults were loaded.
+    pub fn load_with_warning() -> (Self, Option<String>) {
+        let (mut config, warning) = Self::load_file();
+        if let Some(name) = PROFILE_OVERRIDE.get() {
+            config.switch_profile(name);
+        }
+        (config, warning)
+    }
+
+    /// Reads the config file. A missing file gives the defaults quietly; an
+    /// unreadable or malformed one gives them with a warning, and a malformed
+    /// one is first copied to `<config>.corrupt` so its settings can be
+    /// recovered by hand.
+    fn load_file() -> (Self, Option<String>) {
         let config_path = Self::config_path();
-        if config_path.exists() {
-            if let Ok(contents) = fs::read_to_string(&config_path) {
-                if let Ok(config) = serde_json::from_str(&contents) {
-                    return config;
-                }
+        let contents = match fs::read_to_string(&config_path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return (Self::default(), None),
+            Err(e) => {
+                let warning = format!("Couldn't read settings from {}: {}; using defaults", config_path.display(), e);
+                log::error!("{}", warning);
+                return (Self::default(), Some(warning));
+            }
+        };
+        match ConfigFormat::of(&config_path).parse(&contents) {
+            Ok(mut config) => {
+                config.migrate();
+                (config, None)
+            }
+            Err(e) => {
+                let mut backup = config_path.clone().into_os_string();
+                backup.push(".corrupt");
+                let backup = PathBuf::from(backup);
+                let kept = match fs::copy(&config_path, &backup) {
+                    Ok(_) => format!("; the old file was copied to {}", backup.display()),
+                    Err(copy_error) => {
+                        log::error!("Error backing up config to {}: {}", backup.display(), copy_error);
+                        String::new()
+                    }
+                };
+                let warning = format!(
+                    "Settings were reset because {} is invalid ({}){}",
+                    config_path.display(),
+                    e,
+                    kept
+                );
+                log::error!("{}", warning);
+                (Self::default(), Some(warning))
+            }
+        }
+    }
+
+    /// Brings a config read from an older schema version up to
+    /// [`CONFIG_VERSION`], profiles included. Fields added since are already
+    /// filled with defaults; steps here handle renames and changed meanings.
+    fn migrate(&mut self) {
+        if self.version < CONFIG_VERSION {
+            // 0 -> 1: versioning introduced; missing fields took their defaults
+            log::info!("Upgrading config from version {} to {}", self.version, CONFIG_VERSION);
+            self.version = CONFIG_VERSION;
+        } else if self.version > CONFIG_VERSION {
+            log::warn!(
+                "Config was written by a newer version (schema {}); settings this version doesn't know are dropped when it saves",
+                self.version
+            );
+        }
+        for profile in self.profiles.values_mut() {
+            profile.migrate();
+        }
+    }
+
+    /// Makes every later [`load`](Self::load) switch to profile `name`.
+    /// Fails when the config file has no such profile.
+    pub fn set_profile(name: String) -> Result<(), String> {
+        if !Self::load_file().0.has_profile(&name) {
+            return Err(format!("no profile named \"{}\"", name));
+        }
+        let _ = PROFILE_OVERRIDE.set(name);
+        Ok(())
+    }
+
+    pub fn has_profile(&self, name: &str) -> bool {
+        self.active_profile == name || self.profiles.contains_key(name)
+    }
+
+    /// Every profile name, the active one included, in order.
+    pub fn profile_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.profiles.keys().cloned().collect();
+        if !self.profiles.contains_key(&self.active_profile) {
+            names.push(self.active_profile.clone());
+            names.sort();
+        }
+        names
+    }
+
+    /// Saves the current settings as profile `name` and makes it active.
+    pub fn store_profile(&mut self, name: &str) {
+        let mut profile = self.clone();
+        profile.profiles.clear();
+        profile.statistics = Statistics::default();
+        self.profiles.insert(name.to_string(), profile);
+        self.active_profile = name.to_string();
+    }
+
+    /// Stores the current settings under the active profile, then replaces
+    /// them with profile `name`'s. The last run's statistics, the window
+    /// geometry and the profiles themselves are shared by every profile.
+    /// Returns false, changing nothing, when there is no such profile.
+    pub fn switch_profile(&mut self, name: &str) -> bool {
+        if name == self.active_profile {
+            return true;
+        }
+        let Some(profile) = self.profiles.get(name).cloned() else {
+            return false;
+        };
+        let active = self.active_profile.clone();
+        self.store_profile(&active);
+        self.apply_profile(name, profile);
+        true
+    }
+
+    /// Forgets profile `name`; the default profile can't be deleted. Deleting
+    /// the active profile switches to the default one.
+    pub fn delete_profile(&mut self, name: &str) {
+        if name == DEFAULT_PROFILE {
+            return;
+        }
+        self.profiles.remove(name);
+        if self.active_profile == name {
+            match self.profiles.get(DEFAULT_PROFILE).cloned() {
+                Some(profile) => self.apply_profile(DEFAULT_PROFILE, profile),
+                None => self.active_profile = DEFAULT_PROFILE.to_string(),
             }
         }
-        Self::default()
+    }
+
+    fn apply_profile(&mut self, name: &str, profile: AppConfig) {
+        *self = AppConfig {
+            statistics: std::mem::take(&mut self.statistics),
+            window_size: self.window_size,
+            window_pos: self.window_pos,
+            profiles: std::mem::take(&mut self.profiles),
+            active_profile: name.to_string(),
+            ..profile
+        };
     }
 
     pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
         let config_path = Self::config_path();
-        let json = serde_json::to_string_pretty(self)?;
-        fs::write(config_path, json)?;
+        if let Some(parent) = config_path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        let contents = ConfigFormat::of(&config_path).serialize(self)?;
+        fs::write(config_path, contents)?;
         Ok(())
     }
 
-    fn config_path() -> PathBuf {
+    /// Uses `path` as the config file for the rest of the process, in place
+    /// of the environment variable or the default. Only the first call counts.
+    pub fn set_config_path(path: PathBuf) {
+        let _ = CONFIG_OVERRIDE.set(path);
+    }
+
+    /// The config file: the `--config` path, else [`CONFIG_ENV_VAR`], else
+    /// `config.json` in the application's config directory.
+    pub fn config_path() -> PathBuf {
+        if let Some(path) = CONFIG_OVERRIDE.get() {
+            return path.clone();
+        }
+        match std::env::var_os(CONFIG_ENV_VAR) {
+            Some(path) if !path.is_empty() => PathBuf::from(path),
+            _ => Self::data_path("config.json"),
+        }
+    }
+
+    /// Path of `file_name` in the application's config directory, which is
+    /// created if needed.
+    pub fn data_path(file_name: &str) -> PathBuf {
         let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
         path.push("csv-link-extractor");
         fs::create_dir_all(&path).unwrap_or_default();
-        path.push("config.json");
+        path.push(file_name);
         path
     }
+
+    /// Default home for charts and reports: a `statistics` folder in the
+    /// user's data directory, which is writable even for packaged installs.
+    pub fn default_statistics_dir() -> PathBuf {
+        dirs::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("csv-link-extractor")
+            .join("statistics")
+    }
+
+    /// The configured statistics directory, or the default when `dir` is blank.
+    pub fn resolve_statistics_dir(dir: &str) -> PathBuf {
+        match dir.trim() {
+            "" => Self::default_statistics_dir(),
+            dir => PathBuf::from(dir),
+        }
+    }
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
+            version: CONFIG_VERSION,
             directory: String::from("C:\\Users\\AJ\\Downloads\\linkedin-jobs"),
             output: String::from("C:\\Users\\AJ\\Downloads\\all_links.txt"),
             skip_header: false,
-            workers: 4,
+            workers: crate::available_workers().min(16),
             exclude_file: String::new(),
+            exclude_patterns: String::new(),
             continue_on_error: false,
+            read_retries: 2,
+            read_retry_delay_ms: 250,
             master_list_path: String::new(),
+            statistics_dir: Self::default_statistics_dir().to_string_lossy().into_owned(),
             sample_file_path: String::new(),
-            selected_header: String::from("Company Apply Url"),
+            selected_headers: vec![String::from("Company Apply Url")],
             statistics: Statistics::default(),
             use_timestamp: false,
+            report_high_volume: false,
+            high_volume_threshold: 25,
+            write_excluded: false,
+            group_by_domain: false,
+            write_invalid: false,
+            trim: CsvTrim::All,
+            dedup_regex: String::new(),
+            chart_timeout_secs: 30,
+            include_first_seen: false,
+            recursive: false,
+            file_patterns: String::new(),
+            extensions: String::from("csv"),
+            parallel_records: false,
+            split_cells: false,
+            scan_text: false,
+            check_live: false,
+            timeout_secs: 10,
+            delimiter: Delimiter::Auto,
+            encoding: InputEncoding::Auto,
+            output_format: OutputFormat::Txt,
+            line_ending: LineEnding::Lf,
+            with_source: false,
+            count_occurrences: false,
+            normalize: false,
+            tracking_params: String::from("utm_*, fbclid, gclid"),
+            strip_query: false,
+            strip_fragment: false,
+            include_domains: String::new(),
+            exclude_domains: String::new(),
+            chart_top_n: 10,
+            chart_width: 1600,
+            chart_height: 900,
+            chart_format: ChartFormat::Png,
+            max_history_sessions: 500,
+            master_list_backups: 5,
+            rewrite_rules: RewriteRule::defaults(),
+            schemes: String::from("http, https"),
+            fallback_column: None,
+            limit: None,
+            min_url_len: None,
+            max_url_len: None,
+            max_per_domain: None,
+            sort: SortOrder::Alpha,
+            theme: Theme::Dark,
+            append: false,
+            dedup_mode: DedupMode::CaseInsensitiveHost,
+            prune_days: 90,
+            window_size: [400.0, 660.0],
+            window_pos: None,
+            profiles: BTreeMap::new(),
+            active_profile: DEFAULT_PROFILE.to_string(),
+        }
+    }
+}
+
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(header) if header.is_empty() => Vec::new(),
+        OneOrMany::One(header) => vec![header],
+        OneOrMany::Many(headers) => headers,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Settings with two profiles, run statistics and both set and unset
+    /// optional fields.
+    fn sample_config() -> AppConfig {
+        let mut config = AppConfig {
+            limit: Some(500),
+            window_pos: Some([120.0, 80.5]),
+            selected_headers: vec![String::from("Company Apply Url"), String::from("Website")],
+            rewrite_rules: vec![RewriteRule {
+                find: String::from("http://"),
+                replace: String::from("https://"),
+                enabled: true,
+            }],
+            ..AppConfig::default()
+        };
+        config.store_profile("Weekly export");
+        config.limit = None;
+        config.max_per_domain = Some(20);
+        config.store_profile(DEFAULT_PROFILE);
+        config.statistics = Statistics {
+            total_files_processed: 3,
+            total_urls_found: 42,
+            unique_urls: 30,
+            last_run: Some(String::from("2024-03-01 12:30:00")),
+            live_urls: Some(28),
+            ..Statistics::default()
+        };
+        config
+    }
+
+    fn round_trip(file_name: &str, config: &AppConfig) -> AppConfig {
+        let format = ConfigFormat::of(Path::new(file_name));
+        let contents = format.serialize(config).unwrap();
+        format
+            .parse(&contents)
+            .unwrap_or_else(|e| panic!("{}: {}\n{}", file_name, e, contents))
+    }
+
+    fn assert_same(loaded: &AppConfig, config: &AppConfig) {
+        assert_eq!(serde_json::to_value(loaded).unwrap(), serde_json::to_value(config).unwrap());
+        assert_eq!(loaded.profile_names(), ["Default", "Weekly export"]);
+        assert_eq!(loaded.profiles["Weekly export"].limit, Some(500));
+        assert_eq!(loaded.profiles[DEFAULT_PROFILE].limit, None);
+        assert_eq!(loaded.statistics.live_urls, Some(28));
+        assert_eq!(loaded.statistics.dead_urls, None);
+    }
+
+    #[test]
+    fn toml_config_round_trips() {
+        let config = sample_config();
+        assert_same(&round_trip("config.toml", &config), &config);
+    }
+
+    #[test]
+    fn yaml_config_round_trips() {
+        let config = sample_config();
+        assert_same(&round_trip("config.yaml", &config), &config);
+        assert_same(&round_trip("config.YML", &config), &config);
+    }
+
+    #[test]
+    fn unknown_extensions_are_read_as_json() {
+        let config = sample_config();
+        for file_name in ["config.json", "config.ini", "config"] {
+            assert!(matches!(ConfigFormat::of(Path::new(file_name)), ConfigFormat::Json));
+            let contents = ConfigFormat::of(Path::new(file_name)).serialize(&config).unwrap();
+            assert!(serde_json::from_str::<serde_json::Value>(&contents).is_ok());
+            assert_same(&round_trip(file_name, &config), &config);
         }
     }
 }
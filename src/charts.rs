@@ -0,0 +1,515 @@
+use crate::enhanced_stats::{EnhancedStatistics, ProcessingSession};
+use crate::ChartOptions;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Scales pixel sizes laid out for a `base` sized chart to one of `size`, so
+/// fonts and margins keep their proportions at any resolution.
+fn scaler(size: (u32, u32), base: (u32, u32)) -> impl Fn(u32) -> u32 {
+    let scale = (size.0 as f64 / base.0 as f64).min(size.1 as f64 / base.1 as f64);
+    move |px| ((px as f64 * scale).round() as u32).max(1)
+}
+
+/// Whether a chart at `path` should be drawn as SVG rather than PNG.
+fn is_svg(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("svg"))
+}
+
+impl EnhancedStatistics {
+    /// Draws a bar chart of the `top_n` most frequent domains at `size`
+    /// (laid out for the default 1600x900), as SVG for a `.svg` path and PNG
+    /// otherwise.
+    pub fn generate_domain_distribution_chart(
+        &self,
+        output_path: &PathBuf,
+        top_n: usize,
+        size: (u32, u32),
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if is_svg(output_path) {
+            self.draw_domain_distribution_chart(SVGBackend::new(output_path, size).into_drawing_area(), top_n, size)
+        } else {
+            self.draw_domain_distribution_chart(BitMapBackend::new(output_path, size).into_drawing_area(), top_n, size)
+        }
+    }
+
+    fn draw_domain_distribution_chart<DB: DrawingBackend>(
+        &self,
+        root: DrawingArea<DB, Shift>,
+        top_n: usize,
+        size: (u32, u32),
+    ) -> Result<(), Box<dyn std::error::Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        root.fill(&WHITE)?;
+        let px = scaler(size, (1600, 900));
+
+        let top_domains: Vec<_> = self.sorted_domain_frequencies().into_iter().take(top_n.max(1)).collect();
+
+        if top_domains.is_empty() {
+            return Ok(());
+        }
+
+        let bar_count = top_domains.len();
+        let max_freq = top_domains.iter().map(|(_, count)| *count).max().unwrap_or(0) as f64;
+        let max_domain_len = top_domains.iter().map(|(domain, _)| domain.len()).max().unwrap_or(0);
+
+        // Shrink labels as bars get narrower so neighbouring names don't overlap
+        let slot_px = size.0.saturating_sub(px(100)) / bar_count as u32;
+        let label_size = slot_px.clamp(px(8), px(14));
+        
+        // Calculate margins based on domain length and label size
+        let bottom_margin = (max_domain_len as u32 * label_size / 2).min(size.1 * 4 / 9);
+        
+        let mut chart = ChartBuilder::on(&root)
+            .caption(format!("Top {} Domains", bar_count), ("sans-serif", px(30)))
+            .margin_top(px(10))
+            .margin_right(px(40))
+            .margin_left(px(60))
+            .margin_bottom(bottom_margin) // Use calculated bottom margin
+            .x_label_area_size(px(150)) // Increased space for domain labels
+            .y_label_area_size(px(60))
+            .build_cartesian_2d(
+                0f64..bar_count as f64,
+                0f64..max_freq * 1.1,
+            )?;
+
+        chart
+            .configure_mesh()
+            .disable_x_mesh()
+            .bold_line_style(&WHITE.mix(0.3))
+            .y_desc("Frequency")
+            .x_desc("Domain")
+            .x_labels(0)  // Remove default x-axis labels
+            .axis_desc_style(("sans-serif", px(15)))
+            .draw()?;
+
+        // Calculate bar width to leave space between bars; many bars get
+        // relatively wider bars so they stay visible
+        let bar_width = if bar_count > 30 { 0.8 } else { 0.6 };
+        let bar_margin = (1.0 - bar_width) / 2.0;
+
+        // Draw bars with margins
+        for (i, &(domain, count)) in top_domains.iter().enumerate() {
+            let x_start = i as f64 + bar_margin;
+            let x_end = (i as f64) + bar_width + bar_margin;
+            
+            // Draw the bar
+            chart.draw_series(std::iter::once(Rectangle::new(
+                [(x_start, 0.0), (x_end, count as f64)],
+                BLUE.filled(),
+            )))?;
+
+            // Draw domain label centered under the bar
+            let label_x = i as f64 + 0.5;
+            
+            // Create rotated text style
+            let style = TextStyle::from(("sans-serif", label_size))
+                .transform(FontTransform::Rotate270)
+                .color(&BLACK);
+            
+            // Position the label below the x-axis with more space
+            chart.draw_series(std::iter::once(Text::new(
+                domain.to_string(),
+                (label_x, -max_freq * 0.02), // Reduced negative offset to move labels up
+                style,
+            )))?;
+        }
+
+        root.present()?;
+        Ok(())
+    }
+
+    /// Draws total URLs, unique URLs and files processed per session, with a
+    /// legend, at `size` (laid out for the default 800x600), as SVG for a
+    /// `.svg` path and PNG otherwise.
+    pub fn generate_historical_trend_chart(
+        &self,
+        output_path: &PathBuf,
+        size: (u32, u32),
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if is_svg(output_path) {
+            self.draw_historical_trend_chart(SVGBackend::new(output_path, size).into_drawing_area(), size)
+        } else {
+            self.draw_historical_trend_chart(BitMapBackend::new(output_path, size).into_drawing_area(), size)
+        }
+    }
+
+    fn draw_historical_trend_chart<DB: DrawingBackend>(
+        &self,
+        root: DrawingArea<DB, Shift>,
+        size: (u32, u32),
+    ) -> Result<(), Box<dyn std::error::Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        root.fill(&WHITE)?;
+        let px = scaler(size, (800, 600));
+
+        if self.sessions.is_empty() {
+            root.draw(&Text::new(
+                "No processing history yet",
+                (size.0 as i32 * 3 / 8, size.1 as i32 / 2 - 10),
+                ("sans-serif", px(20)).into_font().color(&BLACK),
+            ))?;
+            root.present()?;
+            return Ok(());
+        }
+
+        let mut min_time = self.sessions.iter().map(|s| s.timestamp).min().unwrap();
+        let mut max_time = self.sessions.iter().map(|s| s.timestamp).max().unwrap();
+        // A single session (or several at the same instant) would give a
+        // zero-width time axis
+        if min_time == max_time {
+            min_time -= chrono::Duration::hours(1);
+            max_time += chrono::Duration::hours(1);
+        }
+        let series: [(&str, fn(&ProcessingSession) -> usize, RGBColor); 3] = [
+            ("Total URLs", |s| s.total_urls, BLUE),
+            ("Unique URLs", |s| s.unique_urls, GREEN),
+            ("Files Processed", |s| s.files_processed, RED),
+        ];
+        let max_urls = self
+            .sessions
+            .iter()
+            .flat_map(|s| series.iter().map(move |(_, value, _)| value(s)))
+            .max()
+            .unwrap_or(0);
+
+        let mut chart = ChartBuilder::on(&root)
+            .caption("Historical Processing Trends", ("sans-serif", px(30)))
+            .margin(px(10))
+            .x_label_area_size(px(50))
+            .y_label_area_size(px(60))
+            .build_cartesian_2d(min_time..max_time, 0..(max_urls + max_urls / 10).max(1))?;
+
+        chart
+            .configure_mesh()
+            .x_labels(5)
+            .y_labels(10)
+            .y_desc("Count")
+            .x_desc("Time")
+            .axis_desc_style(("sans-serif", px(15)))
+            .draw()?;
+
+        let (legend_len, line_width) = (px(20) as i32, px(2));
+        for (label, value, color) in series {
+            chart
+                .draw_series(LineSeries::new(
+                    self.sessions.iter().map(|s| (s.timestamp, value(s))),
+                    color.stroke_width(line_width),
+                ))?
+                .label(label)
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + legend_len, y)], color.stroke_width(line_width)));
+            // A line needs two points; mark each session so one run still shows
+            if self.sessions.len() == 1 {
+                chart.draw_series(
+                    self.sessions
+                        .iter()
+                        .map(|s| Circle::new((s.timestamp, value(s)), px(4), color.filled())),
+                )?;
+            }
+        }
+        chart
+            .configure_series_labels()
+            .position(SeriesLabelPosition::UpperLeft)
+            .background_style(&WHITE.mix(0.8))
+            .border_style(&BLACK)
+            .label_font(("sans-serif", px(15)))
+            .draw()?;
+
+        root.present()?;
+        Ok(())
+    }
+
+    /// Draws each of the `top_n` most frequent domains' share of all URLs as
+    /// a donut, with the remaining domains as one "Other" slice, at `size`
+    /// (laid out for the default 800x600), as SVG for a `.svg` path and PNG
+    /// otherwise.
+    pub fn generate_domain_pie_chart(
+        &self,
+        output_path: &PathBuf,
+        top_n: usize,
+        size: (u32, u32),
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if is_svg(output_path) {
+            self.draw_domain_pie_chart(SVGBackend::new(output_path, size).into_drawing_area(), top_n, size)
+        } else {
+            self.draw_domain_pie_chart(BitMapBackend::new(output_path, size).into_drawing_area(), top_n, size)
+        }
+    }
+
+    fn draw_domain_pie_chart<DB: DrawingBackend>(
+        &self,
+        root: DrawingArea<DB, Shift>,
+        top_n: usize,
+        size: (u32, u32),
+    ) -> Result<(), Box<dyn std::error::Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        root.fill(&WHITE)?;
+        let px = scaler(size, (800, 600));
+
+        let domains = self.sorted_domain_frequencies();
+        if domains.is_empty() {
+            return Ok(());
+        }
+        let shown = top_n.max(1).min(domains.len());
+        let mut labels: Vec<String> = domains[..shown].iter().map(|(domain, _)| domain.to_string()).collect();
+        let mut sizes: Vec<f64> = domains[..shown].iter().map(|(_, count)| *count as f64).collect();
+        let other: usize = domains[shown..].iter().map(|(_, count)| count).sum();
+        if other > 0 {
+            labels.push("Other".to_string());
+            sizes.push(other as f64);
+        }
+        let colors: Vec<RGBColor> = (0..sizes.len())
+            .map(|i| if i == shown { RGBColor(160, 160, 160) } else { PIE_COLORS[i % PIE_COLORS.len()] })
+            .collect();
+
+        let root = root.titled("Domain Share", ("sans-serif", px(30)))?;
+        let (width, height) = root.dim_in_pixel();
+        let center = (width as i32 / 2, height as i32 / 2);
+        // Room is left around the donut for the domain labels
+        let radius = width.min(height) as f64 * 0.32;
+        let mut pie = Pie::new(&center, &radius, &sizes, &colors, &labels);
+        pie.donut_hole(radius * 0.45);
+        pie.label_style(("sans-serif", px(13)).into_font().color(&BLACK));
+        pie.percentages(("sans-serif", px(12)).into_font().color(&WHITE));
+        root.draw(&pie)?;
+
+        root.present()?;
+        Ok(())
+    }
+
+    /// Draws how many of `urls` fall in each range of lengths, in
+    /// [`LENGTH_BUCKET`] character steps, at `size` (laid out for the default
+    /// 800x600), as SVG for a `.svg` path and PNG otherwise.
+    pub fn generate_url_length_histogram(
+        &self,
+        urls: &[String],
+        output_path: &PathBuf,
+        size: (u32, u32),
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if is_svg(output_path) {
+            self.draw_url_length_histogram(SVGBackend::new(output_path, size).into_drawing_area(), urls, size)
+        } else {
+            self.draw_url_length_histogram(BitMapBackend::new(output_path, size).into_drawing_area(), urls, size)
+        }
+    }
+
+    fn draw_url_length_histogram<DB: DrawingBackend>(
+        &self,
+        root: DrawingArea<DB, Shift>,
+        urls: &[String],
+        size: (u32, u32),
+    ) -> Result<(), Box<dyn std::error::Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        root.fill(&WHITE)?;
+        let px = scaler(size, (800, 600));
+
+        if urls.is_empty() {
+            root.draw(&Text::new(
+                "No URLs collected in the last run",
+                (size.0 as i32 / 4, size.1 as i32 / 2 - 10),
+                ("sans-serif", px(20)).into_font().color(&BLACK),
+            ))?;
+            root.present()?;
+            return Ok(());
+        }
+
+        let mut counts = [0usize; LENGTH_BUCKETS];
+        for url in urls {
+            counts[(url.chars().count() / LENGTH_BUCKET).min(LENGTH_BUCKETS - 1)] += 1;
+        }
+        // Empty buckets past the longest URL are left off the axis
+        let shown = counts.iter().rposition(|&count| count > 0).unwrap_or(0) + 1;
+        let max_count = counts.iter().copied().max().unwrap_or(0);
+
+        let mut chart = ChartBuilder::on(&root)
+            .caption("URL Length", ("sans-serif", px(30)))
+            .margin(px(10))
+            .x_label_area_size(px(50))
+            .y_label_area_size(px(60))
+            .build_cartesian_2d((0..shown).into_segmented(), 0..(max_count + max_count / 10).max(1))?;
+
+        chart
+            .configure_mesh()
+            .disable_x_mesh()
+            .bold_line_style(&WHITE.mix(0.3))
+            .y_desc("URLs")
+            .x_desc("Length (characters)")
+            .x_labels(shown)
+            .x_label_formatter(&|value| match value {
+                SegmentValue::CenterOf(bucket) => length_bucket_label(*bucket),
+                _ => String::new(),
+            })
+            .label_style(("sans-serif", px(12)))
+            .axis_desc_style(("sans-serif", px(15)))
+            .draw()?;
+
+        chart.draw_series(
+            Histogram::vertical(&chart)
+                .style(BLUE.filled())
+                .margin(px(4))
+                .data(counts[..shown].iter().enumerate().map(|(bucket, &count)| (bucket, count))),
+        )?;
+
+        root.present()?;
+        Ok(())
+    }
+}
+
+/// Width in characters of each URL length histogram bar.
+const LENGTH_BUCKET: usize = 20;
+
+/// Bars in the URL length histogram; the last also holds every longer URL.
+const LENGTH_BUCKETS: usize = 15;
+
+/// Axis label for URL length histogram bar `bucket`, e.g. `40-59` or `280+`.
+fn length_bucket_label(bucket: usize) -> String {
+    let start = bucket * LENGTH_BUCKET;
+    if bucket + 1 == LENGTH_BUCKETS {
+        format!("{}+", start)
+    } else {
+        format!("{}-{}", start, start + LENGTH_BUCKET - 1)
+    }
+}
+
+/// Slice colours for the domain pie chart, reused in order; "Other" is grey.
+const PIE_COLORS: [RGBColor; 10] = [
+    RGBColor(31, 119, 180),
+    RGBColor(255, 127, 14),
+    RGBColor(44, 160, 44),
+    RGBColor(214, 39, 40),
+    RGBColor(148, 103, 189),
+    RGBColor(140, 86, 75),
+    RGBColor(227, 119, 194),
+    RGBColor(188, 189, 34),
+    RGBColor(23, 190, 207),
+    RGBColor(28, 113, 216),
+];
+
+/// One lock per chart file, held while it is drawn, so a chart still
+/// rendering after its time budget ran out finishes before the next run
+/// draws the same file.
+static DOMAIN_CHART: Mutex<()> = Mutex::new(());
+static TREND_CHART: Mutex<()> = Mutex::new(());
+static PIE_CHART: Mutex<()> = Mutex::new(());
+static LENGTH_CHART: Mutex<()> = Mutex::new(());
+
+/// Runs a chart renderer on its own thread, holding `lock`, so a
+/// pathological chart can be abandoned instead of blocking the caller.
+fn spawn_render<F>(lock: &'static Mutex<()>, render: F) -> Receiver<Result<(), String>>
+where
+    F: FnOnce() -> Result<(), Box<dyn std::error::Error>> + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let _drawing = lock.lock().unwrap_or_else(PoisonError::into_inner);
+        let _ = tx.send(render().map_err(|e| e.to_string()));
+    });
+    rx
+}
+
+/// Waits for a spawned render until `deadline`. A chart that overruns is
+/// skipped with a warning and left to finish (or not) in the background,
+/// still holding its lock.
+fn wait_for_render(name: &str, render: Receiver<Result<(), String>>, deadline: Instant) {
+    match render.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => log::warn!("Failed to generate {}: {}", name, e),
+        Err(RecvTimeoutError::Timeout) => {
+            log::warn!("Skipped {}: rendering exceeded the time budget", name)
+        }
+        Err(RecvTimeoutError::Disconnected) => {
+            log::warn!("Failed to generate {}: renderer stopped unexpectedly", name)
+        }
+    }
+}
+
+/// Renders the domain distribution, domain share and historical trend
+/// charts, and the length histogram of `urls`, into `stats_dir`
+/// concurrently, each within the time budget.
+pub fn render_charts(stats: &EnhancedStatistics, urls: &HashSet<String>, stats_dir: &Path, options: &ChartOptions) {
+    let extension = options.format.extension();
+    let domain_chart = stats_dir.join(format!("domain_distribution.{}", extension));
+    let trend_chart = stats_dir.join(format!("historical_trends.{}", extension));
+    let pie_chart = stats_dir.join(format!("domain_pie.{}", extension));
+    let length_chart = stats_dir.join(format!("url_lengths.{}", extension));
+
+    let shared = Arc::new(stats.clone());
+    let deadline = Instant::now() + Duration::from_secs(options.timeout_secs);
+    let (top_n, domain_size) = (options.top_n, (options.width, options.height));
+    let trend_size = (options.width / 2, options.height * 2 / 3);
+    let domain_render = {
+        let stats = Arc::clone(&shared);
+        spawn_render(&DOMAIN_CHART, move || stats.generate_domain_distribution_chart(&domain_chart, top_n, domain_size))
+    };
+    let trend_render = {
+        let stats = Arc::clone(&shared);
+        spawn_render(&TREND_CHART, move || stats.generate_historical_trend_chart(&trend_chart, trend_size))
+    };
+    let pie_render = {
+        let stats = Arc::clone(&shared);
+        spawn_render(&PIE_CHART, move || stats.generate_domain_pie_chart(&pie_chart, top_n, trend_size))
+    };
+    let length_render = {
+        let stats = Arc::clone(&shared);
+        let urls: Vec<String> = urls.iter().cloned().collect();
+        spawn_render(&LENGTH_CHART, move || stats.generate_url_length_histogram(&urls, &length_chart, trend_size))
+    };
+    wait_for_render("domain distribution chart", domain_render, deadline);
+    wait_for_render("domain share chart", pie_render, deadline);
+    wait_for_render("historical trend chart", trend_render, deadline);
+    wait_for_render("URL length histogram", length_render, deadline);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration as ChronoDuration, Local};
+    use std::fs;
+
+    fn stats_with_sessions(count: usize) -> EnhancedStatistics {
+        let mut stats = EnhancedStatistics::new();
+        for i in 0..count {
+            stats.add_session(
+                ProcessingSession {
+                    timestamp: Local::now() - ChronoDuration::hours((count - i) as i64),
+                    total_urls: 100 + i * 10,
+                    unique_urls: 50 + i * 5,
+                    files_processed: 3,
+                    processing_time_secs: 1.5,
+                    invalid_urls: i,
+                    url_lengths: None,
+                },
+                100,
+            );
+        }
+        stats
+    }
+
+    #[test]
+    fn trend_chart_renders_with_zero_one_and_two_sessions() {
+        let dir = std::env::temp_dir().join(format!("csv_link_extractor_trend_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for sessions in 0..=2 {
+            let stats = stats_with_sessions(sessions);
+            for extension in ["png", "svg"] {
+                let path = dir.join(format!("historical_trends_{}.{}", sessions, extension));
+                if let Err(e) = stats.generate_historical_trend_chart(&path, (800, 600)) {
+                    panic!("{} sessions as {}: {}", sessions, extension, e);
+                }
+            }
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
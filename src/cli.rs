@@ -0,0 +1,270 @@
+use crate::app_config::AppConfig;
+use crate::enhanced_stats::{EnhancedStatistics, ProcessingSession, UrlLengthSummary};
+use crate::exclusions::Exclusions;
+use crate::master_list::MasterList;
+use crate::sources::FileFilter;
+use crate::streaming::StreamingWriter;
+use crate::url_utils::{compile_dedup_regex, DomainFilter};
+use crate::{
+    create_starter_file, is_stdout, process_directory, resolve_workers, run_statistics, same_file, scan_headers, write_invalid_report,
+    write_output, write_statistics_files, Args, ChartOptions, ExtractOptions, OutputOptions, ProcessError, Progress, HISTORY_FILE,
+};
+use chrono::Local;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Exit status for errors that stop a run (and for the GUI failing to start).
+pub const EXIT_ERROR: i32 = 1;
+/// Exit status when the directory holds no CSV files to read.
+pub const EXIT_NO_CSV_FILES: i32 = 3;
+/// Exit status for a run that extracted no URLs, with `--fail-on-empty`.
+pub const EXIT_NO_URLS: i32 = 4;
+/// Exit status when files failed to read, without `--continue-on-error`.
+pub const EXIT_FILE_ERRORS: i32 = 5;
+
+/// A failed or unsuccessful run: the message printed to stderr and the
+/// process exit status, one of the codes listed in `--help`.
+pub struct CliError {
+    pub code: i32,
+    pub message: String,
+}
+
+impl From<String> for CliError {
+    fn from(message: String) -> Self {
+        Self { code: EXIT_ERROR, message }
+    }
+}
+
+impl From<ProcessError> for CliError {
+    fn from(error: ProcessError) -> Self {
+        let code = match error {
+            ProcessError::Failed(_) => EXIT_ERROR,
+            ProcessError::NoCsvFiles(_) => EXIT_NO_CSV_FILES,
+        };
+        Self {
+            code,
+            message: error.to_string(),
+        }
+    }
+}
+
+/// Runs one extraction of `directory` from the command line, without the GUI,
+/// and prints a summary table unless `--quiet` is given. When the URLs go to
+/// standard output (`-o -`), messages go to stderr so pipelines stay clean.
+///
+/// Files that failed to read (without `--continue-on-error`) and, with
+/// `--fail-on-empty`, finding no URLs still write the output and statistics
+/// but end the run with an error.
+pub fn run(directory: PathBuf, args: Args) -> Result<(), CliError> {
+    let start_time = Instant::now();
+    let workers = resolve_workers(args.workers);
+    let to_stdout = is_stdout(&args.output);
+    let report = |text: String| {
+        if to_stdout {
+            eprint!("{}", text);
+        } else {
+            print!("{}", text);
+        }
+    };
+
+    let dedup_regex = compile_dedup_regex(args.dedup_regex.as_deref().unwrap_or_default())
+        .map_err(|e| format!("invalid --dedup-regex: {}", e))?;
+    let options = ExtractOptions {
+        skip_header: args.skip_header,
+        no_header: args.no_header,
+        continue_on_error: args.continue_on_error,
+        read_retries: args.read_retries,
+        read_retry_delay_ms: args.read_retry_delay,
+        header_names: args.headers.clone(),
+        columns: args.columns.clone(),
+        trim: args.trim,
+        delimiter: args.delimiter,
+        encoding: args.encoding,
+        dedup_regex,
+        dedup_mode: args.dedup_mode,
+        normalize: args.normalize,
+        tracking_params: args.strip_params.clone(),
+        strip_query: args.strip_query,
+        strip_fragment: args.strip_fragment,
+        domain_filter: DomainFilter::new(&args.include_domains, &args.exclude_domains),
+        rewrites: args
+            .rewrite
+            .chunks_exact(2)
+            .map(|pair| (pair[0].clone(), pair[1].clone()))
+            .collect(),
+        schemes: args.schemes.clone(),
+        fallback_column: args.fallback_column,
+        parallel_records: args.parallel_records,
+        split_cells: args.split_cells,
+        scan_text: args.scan_text,
+        max_records: None,
+        limit: args.limit,
+        min_url_len: args.min_url_len,
+        max_url_len: args.max_url_len,
+        files: FileFilter::new(&args.extensions, &args.patterns, &args.skip_patterns)?,
+    };
+    if args.list_headers {
+        return list_headers(&directory, &args, &options, report);
+    }
+    let exclusions = Exclusions::load(&args.exclude_file, &args.exclude_pattern)?;
+
+    let mut master_list = MasterList::new();
+    master_list.set_dedup_mode(args.dedup_mode);
+    if let Some(path) = &args.master_list {
+        if same_file(&args.output, path) {
+            return Err("the output file and the master list are the same file".to_string().into());
+        }
+        create_starter_file(path)
+            .and_then(|_| master_list.load_from_file(path))
+            .map_err(|e| format!("error loading master list {:?}: {}", path, e))?;
+        if let Some(days) = args.prune_days {
+            let pruned = master_list.prune_older_than(days);
+            if !args.quiet {
+                report(format!("Pruned {} master list entries older than {} days\n", pruned, days));
+            }
+        }
+    }
+
+    let output_options = OutputOptions {
+        format: args.format,
+        eol: args.eol,
+        sort: args.sort,
+        append: args.append,
+        with_source: args.with_source,
+        include_first_seen: args.first_seen,
+        check_live: args.check_live,
+        workers,
+        timeout_secs: args.timeout,
+        write_excluded: args.write_excluded,
+        write_invalid: args.write_invalid,
+        count_occurrences: args.count_occurrences,
+        high_volume_threshold: args.high_volume_threshold,
+        group_by_domain: args.group_by_domain,
+        max_per_domain: args.max_per_domain,
+        dry_run: args.dry_run,
+        diff_against: args.diff_against.clone(),
+    };
+    let progress = Progress::default();
+    let (result, summary) = if args.streaming {
+        let sink = StreamingWriter::create(&args.output, &exclusions, &mut master_list, &options, &output_options)?;
+        // The streaming writer checks the master list itself as URLs arrive
+        let result = process_directory(directory, workers, args.recursive, &options, &progress, Some(&sink), None)?;
+        let summary = sink.finish()?;
+        if args.write_invalid && !args.dry_run {
+            if let Err(e) = write_invalid_report(&args.output.with_file_name("invalid_urls.txt"), &result.file_reports) {
+                log::error!("Error writing invalid URLs: {}", e);
+            }
+        }
+        (result, summary)
+    } else {
+        let result = process_directory(
+            directory,
+            workers,
+            args.recursive,
+            &options,
+            &progress,
+            None,
+            Some(&master_list),
+        )?;
+        let summary = write_output(&result, &exclusions, &mut master_list, &args.output, &output_options)?;
+        (result, summary)
+    };
+    let files_processed = result.file_reports.len();
+    let statistics = run_statistics(
+        files_processed,
+        &result,
+        summary.excluded,
+        start_time,
+        summary.written,
+        summary.live_check,
+    );
+
+    // Command-line runs feed the same history and charts as the GUI
+    if !args.dry_run {
+        let config = AppConfig::load();
+        let history_path = AppConfig::data_path(HISTORY_FILE);
+        let mut enhanced_stats = EnhancedStatistics::load(&history_path);
+        enhanced_stats.add_session(
+            ProcessingSession {
+                timestamp: Local::now(),
+                total_urls: result.total_found,
+                unique_urls: summary.written,
+                files_processed,
+                processing_time_secs: start_time.elapsed().as_secs_f64(),
+                invalid_urls: statistics.invalid_urls,
+                url_lengths: UrlLengthSummary::from_urls(&result.urls),
+            },
+            config.max_history_sessions,
+        );
+        enhanced_stats.update_domain_frequencies(&result.occurrences);
+        if let Err(e) = enhanced_stats.save(&history_path) {
+            log::error!("Error saving statistics history: {}", e);
+        }
+        let stats_dir = args
+            .statistics_dir
+            .clone()
+            .unwrap_or_else(|| AppConfig::resolve_statistics_dir(&config.statistics_dir));
+        let charts = ChartOptions {
+            top_n: config.chart_top_n,
+            timeout_secs: args.chart_timeout,
+            width: args.chart_width.unwrap_or(config.chart_width),
+            height: args.chart_height.unwrap_or(config.chart_height),
+            format: args.chart_format.unwrap_or(config.chart_format),
+        };
+        write_statistics_files(&stats_dir, &enhanced_stats, &statistics, &result.urls, &charts);
+    }
+
+    if !args.quiet {
+        if args.dry_run {
+            report("Dry run: nothing was written\n".to_string());
+        }
+        if args.max_per_domain.is_some() {
+            report(format!("Dropped {} URLs over the per-domain cap\n", summary.domain_capped));
+        }
+        if let Some((added, removed)) = summary.diff {
+            report(format!("Compared with the previous output: {} new, {} removed\n", added, removed));
+        }
+        if args.min_url_len.is_some() || args.max_url_len.is_some() {
+            report(format!("Dropped {} URLs outside the length bounds\n", result.length_filtered()));
+        }
+        if let (true, Some(limit)) = (result.limit_reached, args.limit) {
+            report(format!("Stopped early after collecting {} unique URLs (--limit)\n", limit));
+        }
+        report(statistics.summary_table());
+    }
+
+    let failed_files = result.file_reports.iter().filter(|report| !report.errors.is_empty()).count();
+    if failed_files > 0 && !args.continue_on_error {
+        return Err(CliError {
+            code: EXIT_FILE_ERRORS,
+            message: format!("{} of {} files had errors", failed_files, files_processed),
+        });
+    }
+    if args.fail_on_empty && result.total_found == 0 {
+        return Err(CliError {
+            code: EXIT_NO_URLS,
+            message: "no URLs were extracted".to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Prints each file's name and header row for `--list-headers`, followed by
+/// any requested columns it lacks, without extracting anything.
+fn list_headers(directory: &Path, args: &Args, options: &ExtractOptions, report: impl Fn(String)) -> Result<(), CliError> {
+    let scans = scan_headers(directory, args.recursive, options)?;
+    for scan in &scans {
+        match &scan.headers {
+            Ok(headers) => report(format!("{}: {}\n", scan.file, headers.join(", "))),
+            Err(e) => report(format!("{}: error reading headers: {}\n", scan.file, e)),
+        }
+        if !scan.missing.is_empty() {
+            report(format!("  missing: {}\n", scan.missing.join(", ")));
+        }
+    }
+    if options.columns.is_empty() {
+        let complete = scans.iter().filter(|scan| scan.headers.is_ok() && scan.missing.is_empty()).count();
+        report(format!("{} of {} files have every requested column\n", complete, scans.len()));
+    }
+    Ok(())
+}
@@ -1,9 +1,9 @@
 use chrono::{DateTime, Local};
-use plotters::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::PathBuf;
-use url::Url;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::url_utils::{clean_host, registrable_domain};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessingSession {
@@ -12,6 +12,56 @@ pub struct ProcessingSession {
     pub unique_urls: usize,
     pub files_processed: usize,
     pub processing_time_secs: f64,
+    /// Fields rejected as invalid URLs; absent in history saved before it was tracked
+    #[serde(default)]
+    pub invalid_urls: usize,
+    /// Lengths of the unique URLs collected; absent in older history and for
+    /// streaming runs, which don't keep the URLs
+    #[serde(default)]
+    pub url_lengths: Option<UrlLengthSummary>,
+}
+
+/// Shortest, longest and median length in characters of a set of URLs.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct UrlLengthSummary {
+    pub min: usize,
+    pub max: usize,
+    pub median: f64,
+}
+
+impl UrlLengthSummary {
+    /// Summarizes the lengths of `urls`, or `None` when there are none.
+    pub fn from_urls<'a, I>(urls: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = &'a String>,
+    {
+        let mut lengths: Vec<usize> = urls.into_iter().map(|url| url.chars().count()).collect();
+        if lengths.is_empty() {
+            return None;
+        }
+        lengths.sort_unstable();
+        let mid = lengths.len() / 2;
+        let median = if lengths.len() % 2 == 0 {
+            (lengths[mid - 1] + lengths[mid]) as f64 / 2.0
+        } else {
+            lengths[mid] as f64
+        };
+        Some(Self {
+            min: lengths[0],
+            max: lengths[lengths.len() - 1],
+            median,
+        })
+    }
+}
+
+/// How URLs are spread across domains, from [`EnhancedStatistics::domain_summary`].
+#[derive(Debug, Clone)]
+pub struct DomainSummary {
+    pub domains: usize,
+    pub mean: f64,
+    pub median: f64,
+    /// The most frequent domain, its count and its share of all URLs (0–1)
+    pub top: (String, usize, f64),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -28,132 +78,107 @@ impl EnhancedStatistics {
         }
     }
 
-    pub fn add_session(&mut self, session: ProcessingSession) {
+    /// Loads statistics saved by [`save`](Self::save), starting empty if the
+    /// file is missing or unreadable.
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_else(Self::new)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// Records `session`, dropping the oldest sessions beyond `max_sessions`.
+    pub fn add_session(&mut self, session: ProcessingSession, max_sessions: usize) {
         self.sessions.push(session);
+        let excess = self.sessions.len().saturating_sub(max_sessions.max(1));
+        self.sessions.drain(..excess);
     }
 
-    pub fn update_domain_frequencies(&mut self, urls: &[String]) {
-        for url_str in urls {
-            if let Ok(url) = Url::parse(url_str) {
-                if let Some(domain) = url.host_str() {
-                    // Remove 'www.' prefix if present
-                    let clean_domain = domain.strip_prefix("www.").unwrap_or(domain).to_string();
-                    *self.domain_frequencies.entry(clean_domain).or_insert(0) += 1;
-                }
+    /// Adds each URL's occurrence count to its domain, so a domain linked
+    /// 500 times counts 500 even though the URL is written once.
+    pub fn update_domain_frequencies(&mut self, occurrences: &HashMap<String, usize>) {
+        for (url_str, &count) in occurrences {
+            // Remove 'www.' prefix if present
+            if let Some(clean_domain) = clean_host(url_str) {
+                *self.domain_frequencies.entry(clean_domain).or_insert(0) += count;
             }
         }
     }
 
-    pub fn generate_domain_distribution_chart(&self, output_path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-        let root = BitMapBackend::new(output_path.to_str().unwrap(), (1600, 900)).into_drawing_area();
-        root.fill(&WHITE)?;
-
-        let mut sorted_domains: Vec<_> = self.domain_frequencies.iter().collect();
-        sorted_domains.sort_by(|a, b| b.1.cmp(a.1));
-        let top_domains: Vec<_> = sorted_domains.into_iter().take(10).collect();
+    /// Every domain with its count, most frequent first (ties by name).
+    pub fn sorted_domain_frequencies(&self) -> Vec<(&str, usize)> {
+        let mut domains: Vec<(&str, usize)> = self
+            .domain_frequencies
+            .iter()
+            .map(|(domain, count)| (domain.as_str(), *count))
+            .collect();
+        domains.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        domains
+    }
 
-        if top_domains.is_empty() {
-            return Ok(());
-        }
+    /// Domain count, mean and median URLs per domain, and the top domain's
+    /// share, or `None` before any domain has been recorded.
+    pub fn domain_summary(&self) -> Option<DomainSummary> {
+        let domains = self.sorted_domain_frequencies();
+        let (top_domain, top_count) = *domains.first()?;
+        let total: usize = domains.iter().map(|(_, count)| count).sum();
+        let mut counts: Vec<usize> = domains.iter().map(|(_, count)| *count).collect();
+        counts.sort_unstable();
+        let mid = counts.len() / 2;
+        let median = if counts.len() % 2 == 0 {
+            (counts[mid - 1] + counts[mid]) as f64 / 2.0
+        } else {
+            counts[mid] as f64
+        };
+        Some(DomainSummary {
+            domains: domains.len(),
+            mean: total as f64 / domains.len() as f64,
+            median,
+            top: (top_domain.to_string(), top_count, top_count as f64 / total.max(1) as f64),
+        })
+    }
 
-        let max_freq = top_domains.iter().map(|(_, count)| **count).max().unwrap_or(0) as f64;
-        let max_domain_len = top_domains.iter().map(|(domain, _)| domain.len()).max().unwrap_or(0);
-        
-        // Calculate margins based on domain length
-        let bottom_margin = max_domain_len as u32 * 7; // Increase bottom margin for domain names
-        
-        let mut chart = ChartBuilder::on(&root)
-            .caption("Top 10 Domains", ("sans-serif", 30))
-            .margin_top(10)
-            .margin_right(40)
-            .margin_left(60)
-            .margin_bottom(bottom_margin) // Use calculated bottom margin
-            .x_label_area_size(150) // Increased space for domain labels
-            .y_label_area_size(60)
-            .build_cartesian_2d(
-                0f64..10f64,
-                0f64..max_freq * 1.1,
-            )?;
-
-        chart
-            .configure_mesh()
-            .disable_x_mesh()
-            .bold_line_style(&WHITE.mix(0.3))
-            .y_desc("Frequency")
-            .x_desc("Domain")
-            .x_labels(0)  // Remove default x-axis labels
-            .axis_desc_style(("sans-serif", 15))
-            .draw()?;
-
-        // Calculate bar width to leave space between bars
-        let bar_width = 0.6;  // Make bars slightly narrower
-        let bar_margin = (1.0 - bar_width) / 2.0;
-
-        // Draw bars with margins
-        for (i, (domain, &count)) in top_domains.iter().enumerate() {
-            let x_start = i as f64 + bar_margin;
-            let x_end = (i as f64) + bar_width + bar_margin;
-            
-            // Draw the bar
-            chart.draw_series(std::iter::once(Rectangle::new(
-                [(x_start, 0.0), (x_end, count as f64)],
-                BLUE.filled(),
-            )))?;
-
-            // Draw domain label centered under the bar
-            let label_x = i as f64 + 0.5;
-            
-            // Create rotated text style
-            let style = TextStyle::from(("sans-serif", 14))
-                .transform(FontTransform::Rotate270)
-                .color(&BLACK);
-            
-            // Position the label below the x-axis with more space
-            chart.draw_series(std::iter::once(Text::new(
-                domain.to_string(),
-                (label_x, -max_freq * 0.02), // Reduced negative offset to move labels up
-                style,
-            )))?;
+    /// Writes the full domain frequency table as CSV with a `domain,count` header.
+    pub fn export_domain_frequencies_csv(&self, output_path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+        let mut writer = csv::Writer::from_path(output_path)?;
+        writer.write_record(["domain", "count"])?;
+        for (domain, count) in self.sorted_domain_frequencies() {
+            writer.write_record([domain, &count.to_string()])?;
         }
-
-        root.present()?;
+        writer.flush()?;
         Ok(())
     }
 
-    pub fn generate_historical_trend_chart(&self, output_path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-        let root = BitMapBackend::new(output_path.to_str().unwrap(), (800, 600)).into_drawing_area();
-        root.fill(&WHITE)?;
+    /// Writes every session and the domain frequencies as JSON, for external
+    /// dashboards.
+    pub fn export_json(&self, output_path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+        let file = fs::File::create(output_path)?;
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), self)?;
+        Ok(())
+    }
 
-        if self.sessions.is_empty() {
-            return Ok(());
+    /// Writes the full domain frequency table as a JSON array of
+    /// `{"domain": ..., "count": ...}` objects.
+    pub fn export_domain_frequencies_json(&self, output_path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+        #[derive(Serialize)]
+        struct DomainCount<'a> {
+            domain: &'a str,
+            count: usize,
         }
 
-        let min_time = self.sessions.first().unwrap().timestamp;
-        let max_time = self.sessions.last().unwrap().timestamp;
-        let max_urls = self.sessions.iter().map(|s| s.total_urls).max().unwrap_or(0);
-
-        let mut chart = ChartBuilder::on(&root)
-            .caption("Historical Processing Trends", ("sans-serif", 30))
-            .margin(10)
-            .x_label_area_size(50)
-            .y_label_area_size(60)
-            .build_cartesian_2d(min_time..max_time, 0..max_urls + (max_urls / 10))?;
-
-        chart
-            .configure_mesh()
-            .x_labels(5)
-            .y_labels(10)
-            .y_desc("Number of URLs")
-            .x_desc("Time")
-            .axis_desc_style(("sans-serif", 15))
-            .draw()?;
-
-        chart.draw_series(LineSeries::new(
-            self.sessions.iter().map(|s| (s.timestamp, s.total_urls)),
-            &BLUE,
-        ))?;
-
-        root.present()?;
+        let rows: Vec<DomainCount> = self
+            .sorted_domain_frequencies()
+            .into_iter()
+            .map(|(domain, count)| DomainCount { domain, count })
+            .collect();
+        let file = std::fs::File::create(output_path)?;
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), &rows)?;
         Ok(())
     }
 
@@ -169,12 +194,31 @@ impl EnhancedStatistics {
             report.push_str(&format!("Last Processing Time: {}\n", last_session.timestamp));
             report.push_str(&format!("Last Session URLs Processed: {}\n", last_session.total_urls));
             report.push_str(&format!("Last Session Unique URLs: {}\n", last_session.unique_urls));
+            report.push_str(&format!("Last Session Invalid URLs: {}\n", last_session.invalid_urls));
+            if let Some(lengths) = &last_session.url_lengths {
+                report.push_str(&format!(
+                    "Last Session URL Length: min {}, median {:.1}, max {}\n",
+                    lengths.min, lengths.median, lengths.max
+                ));
+            }
             report.push_str(&format!("Last Session Files Processed: {}\n", last_session.files_processed));
             report.push_str(&format!("Last Session Processing Time: {:.2}s\n", last_session.processing_time_secs));
         }
 
         // Domain statistics
         report.push_str("\n## Domain Statistics\n");
+        if let Some(summary) = self.domain_summary() {
+            let (top_domain, top_count, top_share) = &summary.top;
+            report.push_str(&format!("Unique Domains: {}\n", summary.domains));
+            report.push_str(&format!("Mean URLs per Domain: {:.1}\n", summary.mean));
+            report.push_str(&format!("Median URLs per Domain: {:.1}\n", summary.median));
+            report.push_str(&format!(
+                "Most Frequent Domain: {} ({} URLs, {:.1}% of total)\n\n",
+                top_domain,
+                top_count,
+                top_share * 100.0
+            ));
+        }
         let mut domains: Vec<_> = self.domain_frequencies.iter().collect();
         domains.sort_by(|a, b| b.1.cmp(a.1));
         
@@ -188,6 +232,7 @@ impl EnhancedStatistics {
             report.push_str(&format!("\nSession at {}:\n", session.timestamp));
             report.push_str(&format!("- Total URLs: {}\n", session.total_urls));
             report.push_str(&format!("- Unique URLs: {}\n", session.unique_urls));
+            report.push_str(&format!("- Invalid URLs: {}\n", session.invalid_urls));
             report.push_str(&format!("- Files Processed: {}\n", session.files_processed));
             report.push_str(&format!("- Processing Time: {:.2}s\n", session.processing_time_secs));
         }
@@ -196,3 +241,75 @@ impl EnhancedStatistics {
         Ok(())
     }
 }
+
+/// Groups URLs by registrable domain and returns the domains with more than
+/// `threshold` distinct URLs, most frequent first.
+pub fn high_volume_domains<'a, I>(urls: I, threshold: usize) -> Vec<(String, usize)>
+where
+    I: IntoIterator<Item = &'a String>,
+{
+    let mut per_domain: HashMap<String, HashSet<&'a str>> = HashMap::new();
+    for url in urls {
+        if let Some(domain) = registrable_domain(url) {
+            per_domain.entry(domain).or_default().insert(url.as_str());
+        }
+    }
+
+    let mut domains: Vec<(String, usize)> = per_domain
+        .into_iter()
+        .map(|(domain, urls)| (domain, urls.len()))
+        .filter(|(_, count)| *count > threshold)
+        .collect();
+    domains.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    domains
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn domain_frequency_counts_every_occurrence() {
+        // Two records with the same URL: deduplicated to one URL read twice
+        let occurrences = HashMap::from([(String::from("https://www.example.com/jobs/1"), 2)]);
+        let mut stats = EnhancedStatistics::new();
+        stats.update_domain_frequencies(&occurrences);
+        assert_eq!(stats.domain_frequencies.get("example.com"), Some(&2));
+    }
+
+    #[test]
+    fn domain_frequency_sums_across_urls_and_runs() {
+        let occurrences = HashMap::from([
+            (String::from("https://example.com/jobs/1"), 1),
+            (String::from("https://www.example.com/jobs/2"), 1),
+            (String::from("https://other.org/"), 3),
+        ]);
+        let mut stats = EnhancedStatistics::new();
+        stats.update_domain_frequencies(&occurrences);
+        stats.update_domain_frequencies(&occurrences);
+        assert_eq!(stats.domain_frequencies.get("example.com"), Some(&4));
+        assert_eq!(stats.domain_frequencies.get("other.org"), Some(&6));
+        assert_eq!(stats.sorted_domain_frequencies(), [("other.org", 6), ("example.com", 4)]);
+    }
+
+    #[test]
+    fn high_volume_domains_group_subdomains_under_one_company() {
+        let urls: Vec<String> = [
+            "https://jobs.sap.de/1",
+            "https://careers.sap.de/2",
+            "https://www.sap.de/3",
+            "https://jobs.example.co.uk/1",
+            "https://example.co.uk/2",
+            "https://other.org/1",
+        ]
+        .iter()
+        .map(|url| url.to_string())
+        .collect();
+        assert_eq!(
+            high_volume_domains(&urls, 1),
+            [("sap.de".to_string(), 3), ("example.co.uk".to_string(), 2)]
+        );
+        assert_eq!(high_volume_domains(&urls, 2), [("sap.de".to_string(), 3)]);
+        assert!(high_volume_domains(&urls, 3).is_empty());
+    }
+}
@@ -0,0 +1,102 @@
+use crate::url_utils::normalize_url;
+use regex::Regex;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// URLs to leave out of the output: entries merged from any number of
+/// exclude files, plus regex patterns matched anywhere in the URL.
+///
+/// Entries are compared in their [`normalize_url`] form, so an entry still
+/// matches a URL that differs only in host case, default port or a trailing
+/// slash. Patterns see the URL as written.
+#[derive(Default)]
+pub struct Exclusions {
+    /// Normalized exclude file entries
+    urls: HashSet<String>,
+    patterns: Vec<Regex>,
+}
+
+impl Exclusions {
+    /// Reads every exclude file (one URL per line) and compiles `patterns`.
+    /// Unreadable files are reported and skipped; an invalid pattern is an
+    /// error.
+    pub fn load<P: AsRef<Path>>(files: &[P], patterns: &[String]) -> Result<Self, String> {
+        let mut urls = HashSet::new();
+        for path in files {
+            match fs::read_to_string(path) {
+                Ok(contents) => urls.extend(
+                    contents
+                        .lines()
+                        .map(str::trim)
+                        .filter(|line| !line.is_empty())
+                        .map(|line| normalize_url(line, &[])),
+                ),
+                Err(e) => log::warn!("Error reading exclude file {:?}: {}", path.as_ref(), e),
+            }
+        }
+
+        let patterns = patterns
+            .iter()
+            .map(|p| p.trim())
+            .filter(|p| !p.is_empty())
+            .map(|p| Regex::new(p).map_err(|e| format!("invalid exclude pattern `{}`: {}", p, e)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { urls, patterns })
+    }
+
+    pub fn is_excluded(&self, url: &str) -> bool {
+        self.urls.contains(&normalize_url(url, &[])) || self.patterns.iter().any(|p| p.is_match(url))
+    }
+}
+
+/// Splits the GUI's exclude file field, which accepts a comma- or
+/// newline-separated list of paths.
+pub fn parse_path_list(list: &str) -> Vec<PathBuf> {
+    list.split(|c| c == ',' || c == '\n')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Loads `exclude_file` (its lines) from a temporary file named after `test`.
+    fn exclusions_from(test: &str, exclude_file: &str, patterns: &[&str]) -> Exclusions {
+        let path = std::env::temp_dir().join(format!("csv_link_extractor_{}_{}.txt", test, std::process::id()));
+        fs::write(&path, exclude_file).unwrap();
+        let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+        let exclusions = Exclusions::load(&[&path], &patterns).unwrap();
+        fs::remove_file(&path).unwrap();
+        exclusions
+    }
+
+    #[test]
+    fn trailing_slash_differences_still_match() {
+        let exclusions = exclusions_from("exclude_slash", "https://example.com/jobs/\nhttps://other.org/apply\n", &[]);
+        assert!(exclusions.is_excluded("https://example.com/jobs"));
+        assert!(exclusions.is_excluded("https://example.com/jobs/"));
+        assert!(exclusions.is_excluded("https://other.org/apply/"));
+        assert!(!exclusions.is_excluded("https://example.com/jobs/1"));
+    }
+
+    #[test]
+    fn host_case_differences_still_match() {
+        let exclusions = exclusions_from("exclude_case", "  https://Example.COM/Jobs  \n", &[]);
+        assert!(exclusions.is_excluded("https://example.com/Jobs"));
+        assert!(exclusions.is_excluded("HTTPS://EXAMPLE.com:443/Jobs/"));
+        // The path keeps its case
+        assert!(!exclusions.is_excluded("https://example.com/jobs"));
+    }
+
+    #[test]
+    fn patterns_see_the_url_as_written() {
+        let exclusions = exclusions_from("exclude_patterns", "", &["WWW\\.Example"]);
+        assert!(exclusions.is_excluded("https://WWW.Example.com/"));
+        assert!(!exclusions.is_excluded("https://www.example.com/"));
+    }
+}
@@ -0,0 +1,2383 @@
+use crate::app_config::{
+    AppConfig, ChartFormat, CsvTrim, DedupMode, Delimiter, InputEncoding, LineEnding, OutputFormat, RewriteRule, SortOrder, Statistics, Theme,
+    DEFAULT_PROFILE,
+};
+use crate::enhanced_stats::{DomainSummary, EnhancedStatistics, ProcessingSession, UrlLengthSummary};
+use crate::exclusions::{parse_path_list, Exclusions};
+use crate::master_list::MasterList;
+use crate::sources::{collect_csv_sources, open_input, CsvSource, FileFilter};
+use crate::url_utils::{compile_dedup_regex, is_valid_url, normalize_url, parse_param_list, strip_url_parts, DomainFilter};
+use crate::{
+    build_csv_reader, companion_path, create_starter_file, detect_url_column, extract_urls_from_csv, process_directory, resolve_workers, run_statistics, same_file,
+    scan_headers, urls_per_file, verify_output, write_output, write_statistics_files, ChartOptions, ExtractOptions, FileExtraction, FileHeaders, FileReport,
+    OutputOptions, ProcessingResult, Progress, HISTORY_FILE, URL_COLUMN_SAMPLE_ROWS,
+};
+use chrono::Local;
+use eframe::{egui, App, Frame, NativeOptions, Storage};
+use egui::{CentralPanel, TextEdit, TopBottomPanel};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// Highlight colour shared by both themes: selections, tabs and the Process button.
+const ACCENT_COLOR: egui::Color32 = egui::Color32::from_rgb(28, 113, 216);
+
+/// Records read from the sample CSV for the sample URL table, and the
+/// number of URLs shown from them.
+const SAMPLE_PREVIEW_RECORDS: usize = 200;
+const SAMPLE_PREVIEW_URLS: usize = 20;
+
+/// Shortest time between two writes of the config file while settings change.
+const CONFIG_SAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Values captured when a run starts that are needed to finish it.
+struct PendingRun {
+    start_time: Instant,
+    files_processed: usize,
+    output_path: PathBuf,
+    exclusions: Exclusions,
+    /// Preview only: report what would be written without touching any file
+    dry_run: bool,
+}
+
+/// An extraction running on a background thread.
+struct ProcessingJob {
+    receiver: Receiver<Result<ProcessingResult, String>>,
+    progress: Arc<Progress>,
+    run: PendingRun,
+}
+
+/// Kind of native dialog opened by a "Browse…" button.
+#[derive(PartialEq, Clone, Copy)]
+enum Browse {
+    Folder,
+    OpenFile,
+    OpenFiles,
+    SaveFile,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Tab {
+    Main,
+    Statistics,
+    Log,
+    Settings,
+}
+
+impl Tab {
+    /// Tabs in bar order with their titles; Ctrl+1 to Ctrl+4 follow it.
+    const ALL: [(Tab, &'static str); 4] = [
+        (Tab::Main, "Main"),
+        (Tab::Statistics, "Statistics"),
+        (Tab::Log, "Log"),
+        (Tab::Settings, "Settings"),
+    ];
+}
+
+struct ExportCsvLinksApp {
+    directory: String,
+    output: String,
+    skip_header: bool,
+    workers: usize,
+    exclude_file: String,
+    exclude_patterns: String,
+    continue_on_error: bool,
+    read_retries: u32,
+    read_retry_delay_ms: u64,
+    /// Shared with the extraction thread during a run; changed through
+    /// `Arc::make_mut`, which only copies if a run is still holding it
+    master_list: Arc<MasterList>,
+    master_list_path: String,
+    statistics_dir: String,
+    sample_file_path: String,
+    available_headers: Vec<String>, 
+    selected_headers: Vec<String>,
+    /// Header picked by `detect_url_column` when the sample was loaded, until
+    /// the selection is changed by hand
+    auto_detected_header: Option<String>,
+    config: AppConfig,
+    status_message: String,
+    current_tab: Tab,
+    statistics: Statistics,
+    use_timestamp: bool,
+    enhanced_stats: EnhancedStatistics,
+    /// `enhanced_stats.domain_summary()`, refreshed after each run
+    domain_summary: Option<DomainSummary>,
+    /// The Clear History confirmation is showing
+    confirm_clear_history: bool,
+    /// The Delete profile confirmation is showing
+    confirm_delete_profile: bool,
+    report_high_volume: bool,
+    high_volume_threshold: usize,
+    write_excluded: bool,
+    group_by_domain: bool,
+    write_invalid: bool,
+    autosave_suspended: usize,
+    /// Settings changed since they were last written
+    save_pending: bool,
+    last_saved: Option<Instant>,
+    last_output_path: Option<PathBuf>,
+    trim: CsvTrim,
+    dedup_regex: String,
+    chart_timeout_secs: u64,
+    include_first_seen: bool,
+    recursive: bool,
+    file_patterns: String,
+    extensions: String,
+    parallel_records: bool,
+    split_cells: bool,
+    scan_text: bool,
+    check_live: bool,
+    timeout_secs: u64,
+    delimiter: Delimiter,
+    encoding: InputEncoding,
+    output_format: OutputFormat,
+    line_ending: LineEnding,
+    with_source: bool,
+    count_occurrences: bool,
+    normalize: bool,
+    tracking_params: String,
+    strip_query: bool,
+    strip_fragment: bool,
+    include_domains: String,
+    exclude_domains: String,
+    chart_top_n: usize,
+    chart_width: u32,
+    chart_height: u32,
+    chart_format: ChartFormat,
+    max_history_sessions: usize,
+    master_list_backups: usize,
+    rewrite_rules: Vec<RewriteRule>,
+    schemes: String,
+    fallback_column: Option<usize>,
+    limit: Option<usize>,
+    min_url_len: Option<usize>,
+    max_url_len: Option<usize>,
+    max_per_domain: Option<usize>,
+    sort: SortOrder,
+    theme: Theme,
+    window_size: [f32; 2],
+    window_pos: Option<[f32; 2]>,
+    append: bool,
+    dedup_mode: DedupMode,
+    prune_days: u64,
+    missing_columns: Vec<(String, String)>,
+    file_reports: Vec<FileReport>,
+    log_errors_only: bool,
+    urls_per_file: Vec<(String, usize)>,
+    url_test: String,
+    sample_preview: Option<Result<FileExtraction, String>>,
+    /// Header rows from the last "Scan headers", or why the scan failed
+    header_scan: Option<Result<Vec<FileHeaders>, String>>,
+    /// Name typed for "Save as profile"
+    profile_name: String,
+    job: Option<ProcessingJob>,
+    /// Writes the statistics report and charts off the UI thread
+    stats_writer: Option<JoinHandle<()>>,
+}
+
+impl Default for ExportCsvLinksApp {
+    fn default() -> Self {
+        let (config, warning) = AppConfig::load_with_warning();
+        let mut app = Self::from_config(config);
+        if let Some(warning) = warning {
+            app.status_message = warning;
+        }
+        app
+    }
+}
+
+impl ExportCsvLinksApp {
+    /// Builds the window state from `config`, loading the statistics history,
+    /// master list and sample CSV it points at.
+    fn from_config(config: AppConfig) -> Self {
+        let enhanced_stats = EnhancedStatistics::load(&AppConfig::data_path(HISTORY_FILE));
+        let domain_summary = enhanced_stats.domain_summary();
+        let mut master_list = MasterList::new();
+        master_list.set_max_backups(config.master_list_backups);
+        master_list.set_dedup_mode(config.dedup_mode);
+        
+        // Load master list if path exists
+        if !config.master_list_path.is_empty() && Path::new(&config.master_list_path).exists() {
+            if let Err(e) = master_list.load_from_file(&config.master_list_path) {
+                log::error!("Error loading master list: {}", e);
+            }
+        }
+
+        let mut app = Self {
+            directory: config.directory.clone(),
+            output: config.output.clone(),
+            skip_header: config.skip_header,
+            workers: config.workers,
+            exclude_file: config.exclude_file.clone(),
+            exclude_patterns: config.exclude_patterns.clone(),
+            continue_on_error: config.continue_on_error,
+            read_retries: config.read_retries,
+            read_retry_delay_ms: config.read_retry_delay_ms,
+            master_list: Arc::new(master_list),
+            master_list_path: config.master_list_path.clone(),
+            statistics_dir: config.statistics_dir.clone(),
+            sample_file_path: config.sample_file_path.clone(),
+            available_headers: Vec::new(),
+            selected_headers: config.selected_headers.clone(),
+            auto_detected_header: None,
+            config: config.clone(),
+            status_message: String::from("Ready"),
+            current_tab: Tab::Main,
+            statistics: config.statistics.clone(),
+            use_timestamp: config.use_timestamp,
+            enhanced_stats,
+            domain_summary,
+            confirm_clear_history: false,
+            confirm_delete_profile: false,
+            report_high_volume: config.report_high_volume,
+            high_volume_threshold: config.high_volume_threshold,
+            write_excluded: config.write_excluded,
+            group_by_domain: config.group_by_domain,
+            write_invalid: config.write_invalid,
+            autosave_suspended: 0,
+            save_pending: false,
+            last_saved: None,
+            last_output_path: None,
+            trim: config.trim,
+            dedup_regex: config.dedup_regex.clone(),
+            chart_timeout_secs: config.chart_timeout_secs,
+            include_first_seen: config.include_first_seen,
+            recursive: config.recursive,
+            file_patterns: config.file_patterns.clone(),
+            extensions: config.extensions.clone(),
+            parallel_records: config.parallel_records,
+            split_cells: config.split_cells,
+            scan_text: config.scan_text,
+            check_live: config.check_live,
+            timeout_secs: config.timeout_secs,
+            delimiter: config.delimiter,
+            encoding: config.encoding,
+            output_format: config.output_format,
+            line_ending: config.line_ending,
+            with_source: config.with_source,
+            count_occurrences: config.count_occurrences,
+            normalize: config.normalize,
+            tracking_params: config.tracking_params.clone(),
+            strip_query: config.strip_query,
+            strip_fragment: config.strip_fragment,
+            include_domains: config.include_domains.clone(),
+            exclude_domains: config.exclude_domains.clone(),
+            chart_top_n: config.chart_top_n,
+            chart_width: config.chart_width,
+            chart_height: config.chart_height,
+            chart_format: config.chart_format,
+            max_history_sessions: config.max_history_sessions,
+            master_list_backups: config.master_list_backups,
+            rewrite_rules: config.rewrite_rules.clone(),
+            schemes: config.schemes.clone(),
+            fallback_column: config.fallback_column,
+            limit: config.limit,
+            min_url_len: config.min_url_len,
+            max_url_len: config.max_url_len,
+            max_per_domain: config.max_per_domain,
+            sort: config.sort,
+            theme: config.theme,
+            window_size: config.window_size,
+            window_pos: config.window_pos,
+            append: config.append,
+            dedup_mode: config.dedup_mode,
+            prune_days: config.prune_days,
+            missing_columns: Vec::new(),
+            file_reports: Vec::new(),
+            log_errors_only: false,
+            urls_per_file: Vec::new(),
+            url_test: String::new(),
+            sample_preview: None,
+            header_scan: None,
+            profile_name: String::new(),
+            job: None,
+            stats_writer: None,
+        };
+        
+        app.load_sample_csv();
+        app
+    }
+
+    fn load_sample_csv(&mut self) {
+        let path = Path::new(&self.sample_file_path);
+        if let Ok(file) = open_input(path) {
+            let mut rdr = build_csv_reader(file, self.trim, self.delimiter.for_path(path), self.encoding, true);
+            if let Ok(headers) = rdr.headers() {
+                self.available_headers = headers
+                    .iter()
+                    .map(|h| h.to_string())
+                    .collect();
+                // Drop selected headers that aren't in the sample; if none
+                // remain, select the column that looks most like URLs, or
+                // else the first one
+                let available = &self.available_headers;
+                self.selected_headers.retain(|h| available.contains(h));
+                self.auto_detected_header = None;
+                if self.selected_headers.is_empty() {
+                    let records: Vec<csv::StringRecord> =
+                        rdr.records().take(URL_COLUMN_SAMPLE_ROWS).filter_map(Result::ok).collect();
+                    let detected = detect_url_column(&records, &parse_param_list(&self.schemes))
+                        .and_then(|column| self.available_headers.get(column).cloned());
+                    self.auto_detected_header = detected.clone();
+                    self.selected_headers.extend(detected.or_else(|| self.available_headers.first().cloned()));
+                }
+            }
+        }
+        self.refresh_sample_preview();
+    }
+    fn update(&mut self, ctx: &egui::Context, frame: &mut Frame) {
+        self.poll_processing(ctx);
+        self.poll_stats_writer(ctx);
+        self.track_window_geometry(ctx);
+        self.handle_shortcuts(ctx);
+
+        let dark = match self.theme {
+            Theme::Dark => true,
+            Theme::Light => false,
+            Theme::System => frame.info().system_theme != Some(eframe::Theme::Light),
+        };
+
+        let mut style = (*ctx.style()).clone();
+        if dark {
+            style.visuals = egui::Visuals::dark();
+            style.visuals.override_text_color = Some(egui::Color32::WHITE);
+            style.visuals.extreme_bg_color = egui::Color32::from_rgb(30, 30, 30);
+            style.visuals.widgets.noninteractive.bg_fill = egui::Color32::from_rgb(50, 50, 50);
+        } else {
+            style.visuals = egui::Visuals::light();
+        }
+        style.visuals.selection.bg_fill = ACCENT_COLOR;
+        style.visuals.selection.stroke.color = egui::Color32::WHITE;
+        style.spacing.item_spacing = egui::vec2(10.0, 10.0);
+        style.spacing.window_margin = egui::Margin::same(10.0);
+        style.visuals.window_rounding = egui::Rounding::same(5.0);
+        ctx.set_style(style);
+
+        TopBottomPanel::top("tabs").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                for (number, (tab, name)) in Tab::ALL.into_iter().enumerate() {
+                    if ui
+                        .selectable_label(self.current_tab == tab, name)
+                        .on_hover_text(format!("Ctrl+{}", number + 1))
+                        .clicked()
+                    {
+                        self.current_tab = tab;
+                    }
+                }
+            });
+        });
+
+        // Add spacing after tabs
+        CentralPanel::default().show(ctx, |ui| {
+            ui.add_space(10.0);
+            
+            egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+                match self.current_tab {
+                    Tab::Main => self.render_main_tab(ui),
+                    Tab::Statistics => self.render_statistics_tab(ui),
+                    Tab::Log => self.render_log_tab(ui),
+                    Tab::Settings => self.render_settings_tab(ui),
+                }
+            });
+
+            // Status bar at the bottom
+            ui.with_layout(egui::Layout::bottom_up(egui::Align::LEFT), |ui| {
+                ui.add_space(4.0);
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label(&self.status_message);
+                });
+            });
+        });
+
+        // Check for any UI changes
+        if ctx.input(|i| i.pointer.any_pressed() || i.key_pressed(egui::Key::Enter)) {
+            self.save_config();
+        }
+        self.flush_config(ctx);
+    }
+
+    /// Remembers the window's size and position once the user lets go of it,
+    /// so the next launch opens the same way.
+    fn track_window_geometry(&mut self, ctx: &egui::Context) {
+        let (inner, outer, pointer_down) = ctx.input(|i| {
+            let viewport = i.viewport();
+            (viewport.inner_rect, viewport.outer_rect, i.pointer.any_down())
+        });
+        if pointer_down {
+            return;
+        }
+        let size = inner.map(|rect| [rect.width(), rect.height()]).unwrap_or(self.window_size);
+        let pos = outer.map(|rect| [rect.min.x, rect.min.y]).or(self.window_pos);
+        if size != self.window_size || pos != self.window_pos {
+            self.window_size = size;
+            self.window_pos = pos;
+            self.save_config();
+        }
+    }
+
+    /// Ctrl+Enter processes, Ctrl+1 to Ctrl+4 switch tabs and Esc cancels a
+    /// running job (Cmd instead of Ctrl on macOS).
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        const TAB_KEYS: [egui::Key; 4] = [egui::Key::Num1, egui::Key::Num2, egui::Key::Num3, egui::Key::Num4];
+        let (process, tab, cancel) = ctx.input_mut(|i| {
+            let process = i.consume_key(egui::Modifiers::COMMAND, egui::Key::Enter);
+            let tab = TAB_KEYS.iter().position(|&key| i.consume_key(egui::Modifiers::COMMAND, key));
+            (process, tab, i.key_pressed(egui::Key::Escape))
+        });
+        if let Some(index) = tab {
+            self.current_tab = Tab::ALL[index].0;
+        }
+        if let Some(job) = &self.job {
+            if cancel {
+                job.progress.cancel.store(true, Ordering::Relaxed);
+            }
+        } else if process {
+            self.start_processing(false);
+        }
+    }
+
+    /// Stores the current settings under the active profile and reloads the
+    /// window from profile `name`'s.
+    fn switch_profile(&mut self, name: &str) {
+        if self.job.is_some() {
+            self.status_message = "Wait for the current run to finish before switching profiles".to_string();
+            return;
+        }
+        self.sync_config();
+        let mut config = self.config.clone();
+        if !config.switch_profile(name) {
+            self.status_message = format!("No profile named \"{}\"", name);
+            return;
+        }
+        let current_tab = self.current_tab;
+        // `config` carries these settings on; don't write the old copy on drop
+        self.save_pending = false;
+        *self = Self::from_config(config);
+        self.current_tab = current_tab;
+        self.save_config();
+        self.status_message = format!("Switched to profile \"{}\"", name);
+    }
+
+    /// Saves the current settings as a new or updated profile and makes it
+    /// the active one.
+    fn save_profile(&mut self, name: &str) {
+        self.sync_config();
+        // The profile being left keeps these settings too
+        let active = self.config.active_profile.clone();
+        self.config.store_profile(&active);
+        self.config.store_profile(name);
+        self.save_config();
+        self.status_message = format!("Saved profile \"{}\"", name);
+    }
+
+    fn delete_profile(&mut self, name: &str) {
+        if self.job.is_some() {
+            self.status_message = "Wait for the current run to finish before deleting profiles".to_string();
+            return;
+        }
+        self.sync_config();
+        let mut config = self.config.clone();
+        config.delete_profile(name);
+        let current_tab = self.current_tab;
+        // `config` carries these settings on; don't write the old copy on drop
+        self.save_pending = false;
+        *self = Self::from_config(config);
+        self.current_tab = current_tab;
+        self.save_config();
+        self.status_message = format!("Deleted profile \"{}\"", name);
+    }
+
+    /// Suspends config autosave while a confirmation prompt is open so
+    /// half-edited state is never written.
+    fn suspend_autosave(&mut self) {
+        self.autosave_suspended += 1;
+    }
+
+    /// Resumes autosave; a save requested meanwhile goes out on the next frame.
+    fn resume_autosave(&mut self) {
+        self.autosave_suspended = self.autosave_suspended.saturating_sub(1);
+    }
+
+    /// Writes pending settings once autosave isn't suspended and at most once
+    /// per [`CONFIG_SAVE_INTERVAL`], asking for a repaint to flush any save
+    /// still waiting on the interval.
+    fn flush_config(&mut self, ctx: &egui::Context) {
+        if !self.save_pending || self.autosave_suspended > 0 {
+            return;
+        }
+        let waited = self.last_saved.map_or(CONFIG_SAVE_INTERVAL, |saved| saved.elapsed());
+        if waited >= CONFIG_SAVE_INTERVAL {
+            self.write_config();
+        } else {
+            ctx.request_repaint_after(CONFIG_SAVE_INTERVAL - waited);
+        }
+    }
+
+    /// Shows a native picker starting near `current` and returns the chosen
+    /// path(s). The dialog blocks the UI thread, so no frame (and no
+    /// autosave) runs until it closes; autosave needn't be suspended.
+    fn browse(&self, current: &str, kind: Browse) -> Vec<PathBuf> {
+        let current = Path::new(current.trim());
+        let start = if current.is_dir() { Some(current) } else { current.parent() };
+        let mut dialog = rfd::FileDialog::new();
+        if let Some(dir) = start.filter(|dir| dir.is_dir()) {
+            dialog = dialog.set_directory(dir);
+        }
+        if let Some(name) = current.file_name().filter(|_| kind == Browse::SaveFile) {
+            dialog = dialog.set_file_name(name.to_string_lossy());
+        }
+
+        match kind {
+            Browse::Folder => dialog.pick_folder().into_iter().collect(),
+            Browse::OpenFile => dialog.pick_file().into_iter().collect(),
+            Browse::OpenFiles => dialog.pick_files().unwrap_or_default(),
+            Browse::SaveFile => dialog.save_file().into_iter().collect(),
+        }
+    }
+
+    /// Marks the settings as changed; `flush_config` writes them shortly.
+    fn save_config(&mut self) {
+        self.save_pending = true;
+    }
+
+    /// Writes the settings to the config file now.
+    fn write_config(&mut self) {
+        self.sync_config();
+        self.save_pending = false;
+        self.last_saved = Some(Instant::now());
+        if let Err(e) = self.config.save() {
+            log::error!("Error saving config: {}", e);
+        }
+    }
+
+    /// Copies the window's fields into `config`.
+    fn sync_config(&mut self) {
+        self.config.directory = self.directory.clone();
+        self.config.output = self.output.clone();
+        self.config.skip_header = self.skip_header;
+        self.config.workers = self.workers;
+        self.config.exclude_file = self.exclude_file.clone();
+        self.config.exclude_patterns = self.exclude_patterns.clone();
+        self.config.continue_on_error = self.continue_on_error;
+        self.config.read_retries = self.read_retries;
+        self.config.read_retry_delay_ms = self.read_retry_delay_ms;
+        self.config.master_list_path = self.master_list_path.clone();
+        self.config.statistics_dir = self.statistics_dir.clone();
+        self.config.sample_file_path = self.sample_file_path.clone();
+        self.config.selected_headers = self.selected_headers.clone();
+        self.config.statistics = self.statistics.clone();
+        self.config.use_timestamp = self.use_timestamp;
+        self.config.report_high_volume = self.report_high_volume;
+        self.config.high_volume_threshold = self.high_volume_threshold;
+        self.config.write_excluded = self.write_excluded;
+        self.config.group_by_domain = self.group_by_domain;
+        self.config.write_invalid = self.write_invalid;
+        self.config.trim = self.trim;
+        self.config.dedup_regex = self.dedup_regex.clone();
+        self.config.chart_timeout_secs = self.chart_timeout_secs;
+        self.config.include_first_seen = self.include_first_seen;
+        self.config.recursive = self.recursive;
+        self.config.file_patterns = self.file_patterns.clone();
+        self.config.extensions = self.extensions.clone();
+        self.config.parallel_records = self.parallel_records;
+        self.config.split_cells = self.split_cells;
+        self.config.scan_text = self.scan_text;
+        self.config.check_live = self.check_live;
+        self.config.timeout_secs = self.timeout_secs;
+        self.config.delimiter = self.delimiter;
+        self.config.encoding = self.encoding;
+        self.config.output_format = self.output_format;
+        self.config.line_ending = self.line_ending;
+        self.config.with_source = self.with_source;
+        self.config.count_occurrences = self.count_occurrences;
+        self.config.normalize = self.normalize;
+        self.config.tracking_params = self.tracking_params.clone();
+        self.config.strip_query = self.strip_query;
+        self.config.strip_fragment = self.strip_fragment;
+        self.config.include_domains = self.include_domains.clone();
+        self.config.exclude_domains = self.exclude_domains.clone();
+        self.config.chart_top_n = self.chart_top_n;
+        self.config.chart_width = self.chart_width;
+        self.config.chart_height = self.chart_height;
+        self.config.chart_format = self.chart_format;
+        self.config.max_history_sessions = self.max_history_sessions;
+        self.config.master_list_backups = self.master_list_backups;
+        self.config.rewrite_rules = self.rewrite_rules.clone();
+        self.config.schemes = self.schemes.clone();
+        self.config.fallback_column = self.fallback_column;
+        self.config.limit = self.limit;
+        self.config.min_url_len = self.min_url_len;
+        self.config.max_url_len = self.max_url_len;
+        self.config.max_per_domain = self.max_per_domain;
+        self.config.sort = self.sort;
+        self.config.theme = self.theme;
+        self.config.window_size = self.window_size;
+        self.config.window_pos = self.window_pos;
+        self.config.append = self.append;
+        self.config.dedup_mode = self.dedup_mode;
+        self.config.prune_days = self.prune_days;
+    }
+
+    fn update_statistics(&mut self, 
+        files_processed: usize,
+        result: &ProcessingResult,
+        excluded_count: usize,
+        start_time: std::time::Instant,
+        unique_count: usize,
+        live_check: Option<(usize, usize)>,
+    ) {
+        self.statistics = run_statistics(files_processed, result, excluded_count, start_time, unique_count, live_check);
+        
+        // Save statistics to config
+        self.config.statistics = self.statistics.clone();
+        self.save_config();
+        
+        // Update enhanced statistics
+        let session = ProcessingSession {
+            timestamp: Local::now(),
+            total_urls: result.total_found,
+            unique_urls: unique_count,
+            files_processed,
+            processing_time_secs: start_time.elapsed().as_secs_f64(),
+            invalid_urls: self.statistics.invalid_urls,
+            url_lengths: UrlLengthSummary::from_urls(&result.urls),
+        };
+        
+        self.enhanced_stats.add_session(session, self.max_history_sessions);
+        self.enhanced_stats.update_domain_frequencies(&result.occurrences);
+        self.domain_summary = self.enhanced_stats.domain_summary();
+        if let Err(e) = self.enhanced_stats.save(&AppConfig::data_path(HISTORY_FILE)) {
+            log::error!("Error saving statistics history: {}", e);
+        }
+        
+        // Charts can take seconds to draw, so they're written in the
+        // background; a write still running from the previous run finishes first
+        let stats_dir = AppConfig::resolve_statistics_dir(&self.statistics_dir);
+        let enhanced_stats = self.enhanced_stats.clone();
+        let statistics = self.statistics.clone();
+        let urls = result.urls.clone();
+        let charts = ChartOptions {
+            top_n: self.chart_top_n,
+            timeout_secs: self.chart_timeout_secs,
+            width: self.chart_width,
+            height: self.chart_height,
+            format: self.chart_format,
+        };
+        let previous = self.stats_writer.take();
+        self.stats_writer = Some(std::thread::spawn(move || {
+            if let Some(previous) = previous {
+                let _ = previous.join();
+            }
+            write_statistics_files(&stats_dir, &enhanced_stats, &statistics, &urls, &charts);
+        }));
+    }
+
+    /// Forgets the statistics writer once it has finished, repainting until then.
+    fn poll_stats_writer(&mut self, ctx: &egui::Context) {
+        match &self.stats_writer {
+            Some(writer) if writer.is_finished() => {
+                if let Some(writer) = self.stats_writer.take() {
+                    if writer.join().is_err() {
+                        log::error!("Writing the statistics files failed unexpectedly");
+                    }
+                }
+            }
+            Some(_) => ctx.request_repaint_after(Duration::from_millis(200)),
+            None => {}
+        }
+    }
+
+    fn render_main_tab(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Export CSV Links");
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            ui.label("Directory:");
+            ui.horizontal(|ui| {
+                if ui.add(TextEdit::singleline(&mut self.directory)).changed() {
+                    self.save_config();
+                }
+                if ui.button("Browse…").clicked() {
+                    if let Some(dir) = self.browse(&self.directory, Browse::Folder).pop() {
+                        self.directory = dir.to_string_lossy().into_owned();
+                        self.save_config();
+                    }
+                }
+            });
+
+            ui.label("Output File:");
+            ui.horizontal(|ui| {
+                if ui.add(TextEdit::singleline(&mut self.output)).changed() {
+                    self.save_config();
+                }
+                if ui.button("Browse…").clicked() {
+                    if let Some(file) = self.browse(&self.output, Browse::SaveFile).pop() {
+                        self.output = file.to_string_lossy().into_owned();
+                        self.save_config();
+                    }
+                }
+            });
+
+            ui.label("Exclude Files (comma-separated):");
+            ui.horizontal(|ui| {
+                if ui.add(TextEdit::singleline(&mut self.exclude_file)).changed() {
+                    self.save_config();
+                }
+                if ui.button("Browse…").on_hover_text("Add one or more exclude files").clicked() {
+                    let current = parse_path_list(&self.exclude_file)
+                        .last()
+                        .map(|p| p.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    let picked = self.browse(&current, Browse::OpenFiles);
+                    if !picked.is_empty() {
+                        let mut paths = parse_path_list(&self.exclude_file);
+                        for path in picked {
+                            if !paths.contains(&path) {
+                                paths.push(path);
+                            }
+                        }
+                        let list: Vec<String> = paths.iter().map(|p| p.to_string_lossy().into_owned()).collect();
+                        self.exclude_file = list.join(", ");
+                        self.save_config();
+                    }
+                }
+                if ui.button("Create new…").on_hover_text("Create any listed exclude file that doesn't exist yet").clicked() {
+                    let mut paths = parse_path_list(&self.exclude_file);
+                    if paths.is_empty() {
+                        paths.push(self.starter_path("", "exclude.txt"));
+                    }
+                    let result = paths
+                        .iter()
+                        .filter(|path| !path.exists())
+                        .try_for_each(|path| create_starter_file(path));
+                    match result {
+                        Ok(()) => {
+                            let list: Vec<String> = paths.iter().map(|p| p.to_string_lossy().into_owned()).collect();
+                            self.exclude_file = list.join(", ");
+                            self.status_message = format!("Exclude files ready: {}", self.exclude_file);
+                            self.save_config();
+                        }
+                        Err(e) => self.status_message = format!("Error creating exclude file: {}", e),
+                    }
+                }
+            });
+
+            // Add column selector
+            if !self.available_headers.is_empty() {
+                ui.label("URL Columns:");
+                let mut changed = false;
+                egui::ScrollArea::vertical()
+                    .id_source("header_selector")
+                    .max_height(120.0)
+                    .show(ui, |ui| {
+                        for header in &self.available_headers {
+                            let mut checked = self.selected_headers.contains(header);
+                            ui.horizontal(|ui| {
+                                if ui.checkbox(&mut checked, header).changed() {
+                                    if checked {
+                                        self.selected_headers.push(header.clone());
+                                    } else {
+                                        self.selected_headers.retain(|h| h != header);
+                                    }
+                                    changed = true;
+                                }
+                                if self.auto_detected_header.as_ref() == Some(header) {
+                                    ui.small("(auto-detected)")
+                                        .on_hover_text("Most of this column's sample values are URLs");
+                                }
+                            });
+                        }
+                    });
+                if changed {
+                    self.auto_detected_header = None;
+                    self.save_config();
+                    self.refresh_sample_preview();
+                }
+            }
+
+            ui.horizontal(|ui| {
+                if ui
+                    .button("Scan headers")
+                    .on_hover_text("Read the header row of every file in the directory and check it has the selected columns")
+                    .clicked()
+                {
+                    self.scan_headers();
+                }
+                if self.header_scan.is_some() && ui.small_button("Clear").clicked() {
+                    self.header_scan = None;
+                }
+            });
+            if let Some(scan) = &self.header_scan {
+                show_header_scan(ui, scan);
+            }
+
+            if let Some(preview) = &self.sample_preview {
+                let mut refresh = false;
+                egui::CollapsingHeader::new("Sample URLs")
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        match preview {
+                            Ok(extraction) => {
+                                ui.horizontal(|ui| {
+                                    ui.small(format!(
+                                        "{} URLs and {} invalid fields in the first {} rows",
+                                        extraction.urls.len(),
+                                        extraction.invalid_urls.len(),
+                                        SAMPLE_PREVIEW_RECORDS
+                                    ));
+                                    refresh = ui.small_button("↻").on_hover_text("Refresh").clicked();
+                                });
+                                for column in &extraction.missing_columns {
+                                    ui.colored_label(egui::Color32::from_rgb(220, 80, 80), format!("Column '{}' not found", column));
+                                }
+                                egui::ScrollArea::vertical()
+                                    .id_source("sample_preview")
+                                    .max_height(160.0)
+                                    .show(ui, |ui| {
+                                        egui::Grid::new("sample_preview_grid").striped(true).show(ui, |ui| {
+                                            for (i, url) in extraction.urls.iter().take(SAMPLE_PREVIEW_URLS).enumerate() {
+                                                ui.label(format!("{}", i + 1));
+                                                ui.label(url);
+                                                ui.end_row();
+                                            }
+                                        });
+                                    });
+                            }
+                            Err(e) => {
+                                ui.colored_label(egui::Color32::from_rgb(220, 80, 80), e);
+                            }
+                        }
+                    });
+                if refresh {
+                    self.refresh_sample_preview();
+                }
+            }
+
+            // Style the Process button with better contrast
+            let process_button = egui::Button::new(egui::RichText::new("Process").color(egui::Color32::WHITE))
+                .fill(ACCENT_COLOR)  // Same accent color as tabs
+                .stroke(egui::Stroke::NONE);
+                
+            let running = self.job.is_some();
+            ui.horizontal(|ui| {
+                if ui.add_enabled(!running, process_button).on_hover_text("Ctrl+Enter").clicked() {
+                    self.start_processing(false);
+                }
+                if ui
+                    .add_enabled(!running, egui::Button::new("Preview"))
+                    .on_hover_text("Count the URLs a run would produce without writing any files")
+                    .clicked()
+                {
+                    self.start_processing(true);
+                }
+            });
+
+            // Only offered once a run has actually written the file
+            let output = self.last_output_path.clone().filter(|path| path.exists());
+            ui.horizontal(|ui| {
+                if ui.add_enabled(output.is_some(), egui::Button::new("Open Output")).clicked() {
+                    if let Some(path) = &output {
+                        if let Err(e) = system_open(path) {
+                            self.status_message = format!("Failed to open output file: {}", e);
+                        }
+                    }
+                }
+                if ui.add_enabled(output.is_some(), egui::Button::new("Show in Folder")).clicked() {
+                    if let Some(path) = &output {
+                        if let Err(e) = reveal_in_file_manager(path) {
+                            self.status_message = format!("Failed to show output file: {}", e);
+                        }
+                    }
+                }
+            });
+
+            if let Some(job) = &self.job {
+                let done = job.progress.files_done.load(Ordering::Relaxed);
+                let total = job.progress.files_total.load(Ordering::Relaxed);
+                let fraction = if total > 0 { done as f32 / total as f32 } else { 0.0 };
+                ui.add(egui::ProgressBar::new(fraction).text(format!("{}/{} files", done, total)));
+                if ui.button("Cancel").on_hover_text("Esc").clicked() {
+                    job.progress.cancel.store(true, Ordering::Relaxed);
+                }
+            }
+            ui.small("Shortcuts: Ctrl+Enter to process, Esc to cancel, Ctrl+1–4 to switch tabs");
+        });
+    }
+
+    /// Starts extraction on a background thread; `poll_processing` picks up
+    /// the result and finishes the run on the UI thread.
+    fn start_processing(&mut self, dry_run: bool) {
+        let start_time = Instant::now();
+        
+        let directory_path = PathBuf::from(self.directory.clone());
+        
+        let mut output_path = PathBuf::from(self.output.clone());
+        
+        // Add timestamp to filename if enabled
+        if self.use_timestamp {
+            if let Some(ext) = output_path.extension().and_then(|e| e.to_str()) {
+                if let Some(stem) = output_path.file_stem().and_then(|s| s.to_str()) {
+                    let timestamp = Local::now().format("_%Y%m%d_%H%M%S");
+                    output_path.set_file_name(format!("{}{}.{}", stem, timestamp, ext));
+                }
+            }
+        }
+
+        // Writing the output would truncate the master list it's checked against
+        let master_list_path = self.master_list_path.trim();
+        if !master_list_path.is_empty() && same_file(&output_path, Path::new(master_list_path)) {
+            self.status_message = "Output file and master list are the same file; choose a different output file".to_string();
+            return;
+        }
+
+        let exclude_patterns: Vec<String> = self.exclude_patterns.lines().map(str::to_string).collect();
+        let exclusions = match Exclusions::load(&parse_path_list(&self.exclude_file), &exclude_patterns) {
+            Ok(exclusions) => exclusions,
+            Err(e) => {
+                self.status_message = format!("Error loading exclusions: {}", e);
+                return;
+            }
+        };
+
+        let options = match self.extract_options() {
+            Ok(options) => options,
+            Err(e) => {
+                self.status_message = e;
+                return;
+            }
+        };
+        let files_processed = collect_csv_sources(&directory_path, self.recursive, &options.files)
+            .map(|sources| sources.len())
+            .unwrap_or(0);
+
+        let progress = Arc::new(Progress::default());
+        let (tx, rx) = mpsc::channel();
+        {
+            let progress = Arc::clone(&progress);
+            let workers = resolve_workers(self.workers);
+            let recursive = self.recursive;
+            let master_list = Arc::clone(&self.master_list);
+            std::thread::spawn(move || {
+                // Exclusions are applied when writing so they can be recorded
+                let result =
+                    process_directory(directory_path, workers, recursive, &options, &progress, None, Some(&master_list))
+                        .map_err(|e| e.to_string());
+                // Released before sending so writing the results doesn't copy the list
+                drop(master_list);
+                let _ = tx.send(result);
+            });
+        }
+
+        self.status_message = "Processing...".to_string();
+        self.job = Some(ProcessingJob {
+            receiver: rx,
+            progress,
+            run: PendingRun {
+                start_time,
+                files_processed,
+                output_path,
+                exclusions,
+                dry_run,
+            },
+        });
+    }
+
+    /// Checks on a running job, updating the status bar or finishing the run.
+    fn poll_processing(&mut self, ctx: &egui::Context) {
+        let received = match &self.job {
+            Some(job) => job.receiver.try_recv(),
+            None => return,
+        };
+        match received {
+            Ok(result) => {
+                if let Some(job) = self.job.take() {
+                    if job.progress.cancel.load(Ordering::Relaxed) {
+                        self.status_message = "Processing cancelled".to_string();
+                    } else {
+                        match result {
+                            Ok(result) => self.finish_processing(job.run, result),
+                            Err(e) => self.status_message = e,
+                        }
+                    }
+                }
+            }
+            Err(TryRecvError::Empty) => {
+                if let Some(job) = &self.job {
+                    let done = job.progress.files_done.load(Ordering::Relaxed);
+                    let total = job.progress.files_total.load(Ordering::Relaxed);
+                    self.status_message = format!("Processing... {}/{} files", done, total);
+                }
+                ctx.request_repaint_after(Duration::from_millis(100));
+            }
+            Err(TryRecvError::Disconnected) => {
+                self.job = None;
+                self.status_message = "Processing failed".to_string();
+            }
+        }
+    }
+
+    /// Extraction settings as currently configured in the window.
+    fn extract_options(&self) -> Result<ExtractOptions, String> {
+        let dedup_regex =
+            compile_dedup_regex(&self.dedup_regex).map_err(|e| format!("Invalid dedup regex: {}", e))?;
+        let files = FileFilter::new(&parse_param_list(&self.extensions), &parse_param_list(&self.file_patterns), &[])?;
+        Ok(ExtractOptions {
+            skip_header: self.skip_header,
+            no_header: false,
+            continue_on_error: self.continue_on_error,
+            read_retries: self.read_retries,
+            read_retry_delay_ms: self.read_retry_delay_ms,
+            header_names: self.selected_headers.clone(),
+            columns: Vec::new(),
+            trim: self.trim,
+            delimiter: self.delimiter,
+            encoding: self.encoding,
+            dedup_regex,
+            dedup_mode: self.dedup_mode,
+            normalize: self.normalize,
+            tracking_params: parse_param_list(&self.tracking_params),
+            strip_query: self.strip_query,
+            strip_fragment: self.strip_fragment,
+            domain_filter: DomainFilter::new(self.include_domains.lines(), self.exclude_domains.lines()),
+            rewrites: self.enabled_rewrites(),
+            schemes: parse_param_list(&self.schemes),
+            fallback_column: self.fallback_column,
+            parallel_records: self.parallel_records,
+            split_cells: self.split_cells,
+            scan_text: self.scan_text,
+            max_records: None,
+            limit: self.limit,
+            min_url_len: self.min_url_len,
+            max_url_len: self.max_url_len,
+            files,
+        })
+    }
+
+    /// Re-extracts the first records of the sample CSV with the selected
+    /// columns, for the sample URL table on the Main tab.
+    fn refresh_sample_preview(&mut self) {
+        let path = PathBuf::from(self.sample_file_path.trim());
+        if !path.is_file() {
+            self.sample_preview = None;
+            return;
+        }
+        self.sample_preview = Some(self.extract_options().map(|mut options| {
+            options.max_records = Some(SAMPLE_PREVIEW_RECORDS);
+            options.continue_on_error = true;
+            extract_urls_from_csv(&CsvSource::from_path(path), &options, &AtomicBool::new(false))
+        }));
+    }
+
+    /// Reads the header row of every file a run would read, for the header
+    /// scan table on the Main tab.
+    fn scan_headers(&mut self) {
+        let directory_path = PathBuf::from(self.directory.clone());
+        self.header_scan = Some(
+            self.extract_options()
+                .and_then(|options| scan_headers(&directory_path, self.recursive, &options).map_err(|e| e.to_string())),
+        );
+    }
+
+    /// Enabled rewrite rules as `(find, replace)` pairs, in order.
+    fn enabled_rewrites(&self) -> Vec<(String, String)> {
+        self.rewrite_rules
+            .iter()
+            .filter(|rule| rule.enabled && !rule.find.is_empty())
+            .map(|rule| (rule.find.clone(), rule.replace.clone()))
+            .collect()
+    }
+
+    /// Runs the sample URL from the Settings test pane through the same
+    /// rewrite, validation, domain filter and normalization steps as extraction.
+    fn render_url_test(&mut self, ui: &mut egui::Ui) {
+        ui.label("Test a URL:");
+        ui.add(TextEdit::singleline(&mut self.url_test).hint_text("https://example.com/jobs/123"));
+        let input = self.url_test.trim();
+        if input.is_empty() {
+            return;
+        }
+
+        let pass_color = egui::Color32::from_rgb(80, 180, 80);
+        let fail_color = egui::Color32::from_rgb(220, 80, 80);
+        let url = self
+            .enabled_rewrites()
+            .iter()
+            .fold(input.to_string(), |url, (find, replace)| url.replace(find.as_str(), replace));
+        if url != input {
+            ui.small(format!("Rewritten to {}", url));
+        }
+        if !is_valid_url(&url, &parse_param_list(&self.schemes)) {
+            ui.colored_label(fail_color, "✖ Rejected: not an absolute URL with an accepted scheme");
+            return;
+        }
+
+        let host = Url::parse(&url).ok().and_then(|u| u.host_str().map(str::to_string));
+        ui.colored_label(
+            pass_color,
+            format!("✔ Valid URL (host: {})", host.as_deref().unwrap_or("none")),
+        );
+        let filter = DomainFilter::new(self.include_domains.lines(), self.exclude_domains.lines());
+        if !filter.allows(&url) {
+            ui.colored_label(fail_color, "✖ Dropped by the include/exclude domain lists");
+        } else {
+            let stripped = strip_url_parts(&url, self.strip_query, self.strip_fragment);
+            if self.normalize {
+                ui.small(format!("Normalized: {}", normalize_url(&stripped, &parse_param_list(&self.tracking_params))));
+            } else if stripped != url {
+                ui.small(format!("Stripped: {stripped}"));
+            }
+        }
+    }
+
+    /// Output settings for the current run, taken from the GUI fields.
+    fn output_options(&self, dry_run: bool) -> OutputOptions {
+        OutputOptions {
+            diff_against: None,
+            format: self.output_format,
+            eol: self.line_ending,
+            sort: self.sort,
+            append: self.append,
+            with_source: self.with_source,
+            include_first_seen: self.include_first_seen,
+            check_live: self.check_live,
+            workers: self.workers,
+            timeout_secs: self.timeout_secs,
+            write_excluded: self.write_excluded,
+            write_invalid: self.write_invalid,
+            count_occurrences: self.count_occurrences,
+            high_volume_threshold: self.report_high_volume.then_some(self.high_volume_threshold),
+            group_by_domain: self.group_by_domain,
+            max_per_domain: self.max_per_domain,
+            dry_run,
+        }
+    }
+
+    /// Filters, writes and records the URLs from a completed extraction.
+    fn finish_processing(&mut self, run: PendingRun, result: ProcessingResult) {
+        let PendingRun {
+            start_time,
+            files_processed,
+            output_path,
+            exclusions,
+            dry_run,
+        } = run;
+
+        self.urls_per_file = urls_per_file(&result.sources);
+        self.missing_columns = result.missing_columns.clone();
+        self.file_reports = result.file_reports.clone();
+
+        let options = self.output_options(dry_run);
+        let summary = match write_output(&result, &exclusions, Arc::make_mut(&mut self.master_list), &output_path, &options) {
+            Ok(summary) => summary,
+            Err(e) => {
+                self.status_message = e;
+                return;
+            }
+        };
+        let count = summary.written;
+
+        if dry_run {
+            self.statistics = run_statistics(files_processed, &result, summary.excluded, start_time, count, summary.live_check);
+            self.status_message = format!(
+                "Preview: {} URLs would be written, {} filtered by the master list (nothing saved)",
+                count, summary.master_list_hits
+            );
+            return;
+        }
+
+        self.update_statistics(
+            files_processed,
+            &result,
+            summary.excluded,
+            start_time,
+            count,
+            summary.live_check,
+        );
+
+        self.last_output_path = Some(output_path.clone());
+        self.status_message = format!("Processed {} unique URLs", count);
+        if result.limit_reached {
+            self.status_message.push_str(" (stopped at the URL limit)");
+        }
+        let length_filtered = result.length_filtered();
+        if length_filtered > 0 {
+            self.status_message.push_str(&format!(", {} dropped by the length filter", length_filtered));
+        }
+        if summary.domain_capped > 0 {
+            self.status_message.push_str(&format!(", {} over the per-domain cap", summary.domain_capped));
+        }
+        if !self.missing_columns.is_empty() {
+            self.status_message.push_str(&format!(
+                " ({} missing column(s), see Statistics)",
+                self.missing_columns.len()
+            ));
+        }
+    }
+
+    fn render_statistics_tab(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.heading("Statistics Dashboard");
+            if ui
+                .button("🔄")
+                .on_hover_text("Reset Last Run: clear the counts below (history and charts are kept)")
+                .clicked()
+            {
+                self.statistics = Statistics {
+                    total_files_processed: 0,
+                    total_urls_found: 0,
+                    distinct_urls: 0,
+                    unique_urls: 0,
+                    excluded_urls: 0,
+                    invalid_urls: 0,
+                    duplicate_urls: 0,
+                    file_errors: 0,
+                    processing_time: 0.0,
+                    last_run: None,
+                    live_urls: None,
+                    dead_urls: None,
+                };
+                self.config.statistics = self.statistics.clone();
+                self.save_config();
+            }
+            if ui
+                .button("🗑")
+                .on_hover_text("Clear History: forget every past session and domain count used by the charts and report")
+                .clicked()
+            {
+                if !self.confirm_clear_history {
+                    self.confirm_clear_history = true;
+                    self.suspend_autosave();
+                }
+            }
+            // Try a more general and visible cleaning symbol
+            if ui.button("⚡").on_hover_text("Clean Master List").clicked() {
+                if self.master_list.is_loaded() {
+                    let removed = Arc::make_mut(&mut self.master_list).deduplicate();
+                    if let Err(e) = self.master_list.save() {
+                        self.status_message = format!("Error saving master list after cleaning: {}", e);
+                    } else {
+                        self.status_message = format!("Master list cleaned: {} duplicate entries removed", removed);
+                    }
+                } else {
+                    self.status_message = "No master list loaded".to_string();
+                }
+            }
+            if ui.button("↶").on_hover_text("Undo Clean (restore the latest master list backup)").clicked() {
+                self.status_message = match Arc::make_mut(&mut self.master_list).restore_backup() {
+                    Ok(Some(backup)) => format!("Master list restored from {}", backup.display()),
+                    Ok(None) => "No master list backup to restore".to_string(),
+                    Err(e) => format!("Error restoring master list: {}", e),
+                };
+            }
+            if ui.button("✔").on_hover_text("Verify Output Against Master List").clicked() {
+                self.verify_output_against_master_list();
+            }
+        });
+        
+        if self.confirm_clear_history {
+            ui.horizontal(|ui| {
+                ui.colored_label(
+                    egui::Color32::from_rgb(220, 80, 80),
+                    format!("Clear all {} sessions and domain counts?", self.enhanced_stats.sessions.len()),
+                );
+                if ui.button("Clear History").clicked() {
+                    self.clear_history();
+                    self.confirm_clear_history = false;
+                    self.resume_autosave();
+                }
+                if ui.button("Cancel").clicked() {
+                    self.confirm_clear_history = false;
+                    self.resume_autosave();
+                }
+            });
+        }
+
+        ui.add_space(10.0);
+        egui::Grid::new("stats_grid")
+            .num_columns(2)
+            .spacing([40.0, 4.0])
+            .show(ui, |ui| {
+                ui.label("Total Files Processed:");
+                ui.label(format!("{}", self.statistics.total_files_processed));
+                ui.end_row();
+
+                ui.label("Total URLs Found:");
+                ui.label(format!("{}", self.statistics.total_urls_found));
+                ui.end_row();
+
+                ui.label("Unique URLs:");
+                ui.label(format!("{}", self.statistics.unique_urls));
+                ui.end_row();
+
+                ui.label("Excluded URLs:");
+                ui.label(format!("{}", self.statistics.excluded_urls));
+                ui.end_row();
+
+                ui.label("Duplicate URLs:");
+                ui.label(format!("{}", self.statistics.duplicate_urls));
+                ui.end_row();
+
+                ui.label("Invalid URLs:");
+                ui.label(format!("{}", self.statistics.invalid_urls));
+                ui.end_row();
+
+                if let (Some(live), Some(dead)) = (self.statistics.live_urls, self.statistics.dead_urls) {
+                    ui.label("Live URLs:");
+                    ui.label(format!("{}", live));
+                    ui.end_row();
+
+                    ui.label("Dead URLs:");
+                    ui.label(format!("{}", dead));
+                    ui.end_row();
+                }
+
+                ui.label("Processing Time:");
+                ui.label(format!("{:.2}s", self.statistics.processing_time));
+                ui.end_row();
+
+                if let Some(last_run) = &self.statistics.last_run {
+                    ui.label("Last Run:");
+                    ui.label(last_run);
+                    ui.end_row();
+                }
+
+                if let Some(summary) = &self.domain_summary {
+                    let (top_domain, _, top_share) = &summary.top;
+                    ui.label("Domains (all runs):");
+                    ui.label(format!(
+                        "{} (mean {:.1}, median {:.1} URLs each)",
+                        summary.domains, summary.mean, summary.median
+                    ));
+                    ui.end_row();
+
+                    ui.label("Top Domain:");
+                    ui.label(format!("{} ({:.1}%)", top_domain, top_share * 100.0));
+                    ui.end_row();
+                }
+            });
+        
+        if !self.urls_per_file.is_empty() {
+            ui.add_space(10.0);
+            egui::CollapsingHeader::new("URLs per File").show(ui, |ui| {
+                egui::Grid::new("urls_per_file_grid")
+                    .num_columns(2)
+                    .spacing([40.0, 4.0])
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (file, count) in &self.urls_per_file {
+                            ui.label(file);
+                            ui.label(format!("{}", count));
+                            ui.end_row();
+                        }
+                    });
+            });
+        }
+
+        if !self.missing_columns.is_empty() {
+            ui.add_space(10.0);
+            egui::CollapsingHeader::new(format!("Missing Columns ({})", self.missing_columns.len())).show(ui, |ui| {
+                egui::Grid::new("missing_columns_grid")
+                    .num_columns(2)
+                    .spacing([40.0, 4.0])
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (file, column) in &self.missing_columns {
+                            ui.label(file);
+                            ui.label(column);
+                            ui.end_row();
+                        }
+                    });
+            });
+        }
+
+        ui.add_space(20.0);
+        ui.heading("Enhanced Statistics");
+        
+        ui.horizontal(|ui| {
+            if ui.button("Open Statistics Directory").clicked() {
+                let stats_dir = AppConfig::resolve_statistics_dir(&self.statistics_dir);
+                if let Err(e) = fs::create_dir_all(&stats_dir).and_then(|_| open_in_file_manager(&stats_dir)) {
+                    self.status_message = format!("Failed to open statistics directory: {}", e);
+                }
+            }
+            if ui.button("Export Domain Data").clicked() {
+                self.export_domain_data();
+            }
+            if ui.button("Export JSON").on_hover_text("Write every session and domain count to statistics.json").clicked() {
+                self.export_statistics_json();
+            }
+        });
+        
+        ui.horizontal(|ui| {
+            ui.label("Domains in chart:");
+            if ui.add(egui::Slider::new(&mut self.chart_top_n, 5..=50).integer()).changed() {
+                self.save_config();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Chart size:");
+            let width = ui.add(egui::DragValue::new(&mut self.chart_width).clamp_range(400..=8000).suffix(" px"));
+            ui.label("×");
+            let height = ui.add(egui::DragValue::new(&mut self.chart_height).clamp_range(300..=6000).suffix(" px"));
+            if width.changed() || height.changed() {
+                self.save_config();
+            }
+        })
+        .response
+        .on_hover_text("Domain chart size; the trend chart is half as wide and two thirds as tall");
+        ui.horizontal(|ui| {
+            ui.label("Chart format:");
+            let mut chart_format = self.chart_format;
+            egui::ComboBox::from_id_source("chart_format_selector")
+                .selected_text(chart_format.label())
+                .show_ui(ui, |ui| {
+                    for option in ChartFormat::ALL {
+                        ui.selectable_value(&mut chart_format, option, option.label());
+                    }
+                });
+            if chart_format != self.chart_format {
+                self.chart_format = chart_format;
+                self.save_config();
+            }
+        });
+
+        ui.add_space(10.0);
+        if self.stats_writer.is_some() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Rendering charts…");
+            });
+        }
+        ui.label(format!(
+            "Enhanced statistics are available in {}:",
+            AppConfig::resolve_statistics_dir(&self.statistics_dir).display()
+        ));
+        let extension = self.chart_format.extension();
+        ui.label(format!("- Domain distribution chart (domain_distribution.{})", extension));
+        ui.label(format!("- Historical trends chart (historical_trends.{})", extension));
+        ui.label(format!("- Domain share pie chart (domain_pie.{})", extension));
+        ui.label(format!("- URL length histogram (url_lengths.{})", extension));
+        ui.label("- Detailed statistics report (statistics_report.md)");
+        ui.label("- Latest run summary (latest_run.json)");
+        ui.label("- Full domain table (domain_frequencies.csv / .json, via Export Domain Data)");
+        ui.label("- Full session history (statistics.json, via Export JSON)");
+    }
+
+    /// Forgets every recorded session and domain count and deletes the saved
+    /// history, so the next charts and report start from scratch.
+    fn clear_history(&mut self) {
+        self.enhanced_stats = EnhancedStatistics::new();
+        self.domain_summary = None;
+        let history_path = AppConfig::data_path(HISTORY_FILE);
+        self.status_message = match fs::remove_file(&history_path) {
+            Ok(()) => "Statistics history cleared".to_string(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => "Statistics history cleared".to_string(),
+            Err(e) => format!("Error deleting {}: {}", history_path.display(), e),
+        };
+    }
+
+    /// Merges the list at `path` into the loaded master list and saves it.
+    fn merge_master_list(&mut self, path: &Path) {
+        let master_list = Arc::make_mut(&mut self.master_list);
+        self.status_message = match master_list.merge_from_file(path) {
+            Ok(added) => match master_list.save() {
+                Ok(()) => format!("Merged {}: {} new entries added", path.display(), added),
+                Err(e) => format!("Error saving master list after merging: {}", e),
+            },
+            Err(e) => format!("Error reading master list {}: {}", path.display(), e),
+        };
+    }
+
+    fn prune_master_list(&mut self) {
+        if !self.master_list.is_loaded() {
+            self.status_message = "No master list loaded".to_string();
+            return;
+        }
+        let removed = Arc::make_mut(&mut self.master_list).prune_older_than(self.prune_days);
+        self.status_message = match self.master_list.save() {
+            Ok(()) => format!("Pruned {} master list entries older than {} days", removed, self.prune_days),
+            Err(e) => format!("Error saving master list after pruning: {}", e),
+        };
+    }
+
+    /// Writes the full domain frequency table to `statistics/domain_frequencies.{csv,json}`.
+    fn export_domain_data(&mut self) {
+        let stats_dir = AppConfig::resolve_statistics_dir(&self.statistics_dir);
+        let csv_path = stats_dir.join("domain_frequencies.csv");
+        let json_path = stats_dir.join("domain_frequencies.json");
+        let result = fs::create_dir_all(&stats_dir)
+            .map_err(Box::<dyn std::error::Error>::from)
+            .and_then(|_| self.enhanced_stats.export_domain_frequencies_csv(&csv_path))
+            .and_then(|_| self.enhanced_stats.export_domain_frequencies_json(&json_path));
+        self.status_message = match result {
+            Ok(()) => format!(
+                "Exported {} domains to {}",
+                self.enhanced_stats.domain_frequencies.len(),
+                stats_dir.display()
+            ),
+            Err(e) => format!("Error exporting domain data: {}", e),
+        };
+    }
+
+    fn export_statistics_json(&mut self) {
+        let stats_dir = AppConfig::resolve_statistics_dir(&self.statistics_dir);
+        let json_path = stats_dir.join("statistics.json");
+        let result = fs::create_dir_all(&stats_dir)
+            .map_err(Box::<dyn std::error::Error>::from)
+            .and_then(|_| self.enhanced_stats.export_json(&json_path));
+        self.status_message = match result {
+            Ok(()) => format!(
+                "Exported {} sessions to {}",
+                self.enhanced_stats.sessions.len(),
+                json_path.display()
+            ),
+            Err(e) => format!("Error exporting statistics: {}", e),
+        };
+    }
+
+    fn render_log_tab(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Processing Log");
+        if self.file_reports.is_empty() {
+            ui.label("No files processed yet");
+            return;
+        }
+
+        let failed = self.file_reports.iter().filter(|r| !r.errors.is_empty()).count();
+        ui.label(format!("{} files, {} with errors", self.file_reports.len(), failed));
+        ui.checkbox(&mut self.log_errors_only, "Only show files with errors");
+        ui.add_space(10.0);
+
+        let error_color = egui::Color32::from_rgb(220, 80, 80);
+        egui::Grid::new("file_log_grid")
+            .num_columns(4)
+            .spacing([20.0, 4.0])
+            .striped(true)
+            .show(ui, |ui| {
+                ui.strong("File");
+                ui.strong("URLs");
+                ui.strong("Invalid");
+                ui.strong("Errors");
+                ui.end_row();
+                for report in &self.file_reports {
+                    if self.log_errors_only && report.errors.is_empty() {
+                        continue;
+                    }
+                    ui.label(&report.file);
+                    ui.label(format!("{}", report.urls_found));
+                    ui.label(format!("{}", report.invalid_urls.len()));
+                    if report.errors.is_empty() {
+                        ui.label("—");
+                    } else {
+                        ui.vertical(|ui| {
+                            for error in &report.errors {
+                                ui.colored_label(error_color, error);
+                            }
+                        });
+                    }
+                    ui.end_row();
+                }
+            });
+    }
+
+    /// Path for a newly created starter file: the typed path if there is one,
+    /// otherwise `default_name` next to the output file.
+    fn starter_path(&self, current: &str, default_name: &str) -> PathBuf {
+        if !current.trim().is_empty() {
+            return PathBuf::from(current.trim());
+        }
+        Path::new(&self.output)
+            .parent()
+            .map(|dir| dir.join(default_name))
+            .unwrap_or_else(|| PathBuf::from(default_name))
+    }
+
+    fn verify_output_against_master_list(&mut self) {
+        let output_path = self
+            .last_output_path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(&self.output));
+
+        match verify_output(&output_path, &self.master_list) {
+            Ok(mismatches) if mismatches.is_empty() => {
+                self.status_message = "Output is consistent with the master list".to_string();
+            }
+            Ok(mismatches) => {
+                let report_path = companion_path(&output_path, "verify.txt");
+                // The master entry's first-seen time shows which run recorded the other form
+                let report: String = mismatches
+                    .iter()
+                    .map(|(url, entry)| {
+                        let first_seen = self.master_list.first_seen(entry).map(|t| t.to_rfc3339()).unwrap_or_default();
+                        format!("{}\t{}\t{}\n", url, entry, first_seen)
+                    })
+                    .collect();
+                if let Err(e) = fs::write(&report_path, report) {
+                    log::error!("Error writing verification report: {}", e);
+                }
+                self.status_message = format!(
+                    "{} output URLs differ from master list entries only by normalization (see {})",
+                    mismatches.len(),
+                    report_path.display()
+                );
+            }
+            Err(e) => {
+                self.status_message = format!("Error reading output file: {}", e);
+            }
+        }
+    }
+
+    fn render_settings_tab(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Settings");
+        ui.add_space(10.0);
+
+        ui.horizontal(|ui| {
+            ui.label("Profile:");
+            let active = self.config.active_profile.clone();
+            let mut selected = active.clone();
+            egui::ComboBox::from_id_source("profile_selector")
+                .selected_text(&selected)
+                .show_ui(ui, |ui| {
+                    for name in self.config.profile_names() {
+                        ui.selectable_value(&mut selected, name.clone(), name);
+                    }
+                });
+            if selected != active {
+                self.switch_profile(&selected);
+            }
+            if ui
+                .add_enabled(active != DEFAULT_PROFILE, egui::Button::new("Delete"))
+                .on_hover_text("Forget this profile and switch to the default one")
+                .clicked()
+                && !self.confirm_delete_profile
+            {
+                self.confirm_delete_profile = true;
+                self.suspend_autosave();
+            }
+        });
+        if self.confirm_delete_profile {
+            let active = self.config.active_profile.clone();
+            ui.horizontal(|ui| {
+                ui.colored_label(egui::Color32::from_rgb(220, 80, 80), format!("Delete profile \"{}\"?", active));
+                if ui.button("Delete").clicked() {
+                    self.confirm_delete_profile = false;
+                    self.resume_autosave();
+                    self.delete_profile(&active);
+                }
+                if ui.button("Cancel").clicked() {
+                    self.confirm_delete_profile = false;
+                    self.resume_autosave();
+                }
+            });
+        }
+        ui.horizontal(|ui| {
+            ui.add(TextEdit::singleline(&mut self.profile_name).hint_text("Profile name").desired_width(140.0));
+            let name = self.profile_name.trim().to_string();
+            if ui
+                .add_enabled(!name.is_empty(), egui::Button::new("Save as Profile"))
+                .on_hover_text("Save the current settings under this name and switch to it")
+                .clicked()
+            {
+                self.save_profile(&name);
+                self.profile_name.clear();
+            }
+        });
+        ui.small("Each profile keeps its own folders, columns, filters and output settings; switching saves the current profile first");
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Theme:");
+            let mut theme = self.theme;
+            egui::ComboBox::from_id_source("theme_selector")
+                .selected_text(theme.label())
+                .show_ui(ui, |ui| {
+                    for option in Theme::ALL {
+                        ui.selectable_value(&mut theme, option, option.label());
+                    }
+                });
+            if theme != self.theme {
+                self.theme = theme;
+                self.save_config();
+            }
+        });
+        ui.add_space(10.0);
+
+        // Add timestamp checkbox near the top
+        if ui.checkbox(&mut self.use_timestamp, "Add timestamp to output filename").changed() {
+            self.save_config();
+        }
+        if self.use_timestamp {
+            ui.small("Example: output_20240216_235959.txt");
+        }
+
+        ui.label("Output Format:");
+        let mut output_format = self.output_format;
+        egui::ComboBox::from_id_source("format_selector")
+            .selected_text(output_format.label())
+            .show_ui(ui, |ui| {
+                for option in OutputFormat::ALL {
+                    ui.selectable_value(&mut output_format, option, option.label());
+                }
+            });
+        if output_format != self.output_format {
+            self.output_format = output_format;
+            self.save_config();
+        }
+
+        ui.label("Line Endings:");
+        let mut line_ending = self.line_ending;
+        egui::ComboBox::from_id_source("line_ending_selector")
+            .selected_text(line_ending.label())
+            .show_ui(ui, |ui| {
+                for option in LineEnding::ALL {
+                    ui.selectable_value(&mut line_ending, option, option.label());
+                }
+            });
+        if line_ending != self.line_ending {
+            self.line_ending = line_ending;
+            self.save_config();
+        }
+
+        ui.label("Sort Output:");
+        let mut sort = self.sort;
+        egui::ComboBox::from_id_source("sort_selector")
+            .selected_text(sort.label())
+            .show_ui(ui, |ui| {
+                for option in SortOrder::ALL {
+                    ui.selectable_value(&mut sort, option, option.label());
+                }
+            });
+        if sort != self.sort {
+            self.sort = sort;
+            self.save_config();
+        }
+
+        ui.add_enabled_ui(self.output_format != OutputFormat::Json, |ui| {
+            if ui
+                .checkbox(&mut self.append, "Append to existing output file")
+                .on_disabled_hover_text("Appending isn't supported for JSON output")
+                .changed()
+            {
+                self.save_config();
+            }
+        });
+
+        if ui.checkbox(&mut self.with_source, "Write source file for each URL").changed() {
+            self.save_config();
+        }
+        if self.with_source && self.output_format == OutputFormat::Txt {
+            ui.small("Text output becomes one url<TAB>file line per source");
+        }
+
+        if ui.checkbox(&mut self.count_occurrences, "Count occurrences").changed() {
+            self.save_config();
+        }
+        if self.count_occurrences {
+            ui.small("Adds how often each URL appeared across all files; most frequent first");
+        }
+
+        if ui.checkbox(&mut self.include_first_seen, "Include first-seen timestamp in output").changed() {
+            self.save_config();
+        }
+
+        ui.add_space(10.0);
+
+        // Move worker count setting here
+        ui.label("Workers:");
+        if ui.add(egui::Slider::new(&mut self.workers, 1..=16).integer()).changed() {
+            self.save_config();
+        }
+
+        if ui.checkbox(&mut self.recursive, "Scan subdirectories").changed() {
+            self.save_config();
+        }
+        ui.horizontal(|ui| {
+            ui.label("File pattern:");
+            if ui
+                .add(TextEdit::singleline(&mut self.file_patterns).hint_text("*.csv"))
+                .changed()
+            {
+                self.save_config();
+            }
+        });
+        ui.small("Only read files whose name matches, e.g. export_*.csv (comma-separated)");
+        ui.horizontal(|ui| {
+            ui.label("Extensions:");
+            if ui
+                .add(TextEdit::singleline(&mut self.extensions).hint_text("csv"))
+                .changed()
+            {
+                self.save_config();
+            }
+        });
+        ui.small("Comma-separated, e.g. csv, tsv, txt; .tsv files are read tab-separated when the delimiter is auto-detected");
+        if ui
+            .checkbox(&mut self.parallel_records, "Parallelize within files")
+            .on_hover_text("Split each file's records across the workers; helps when one large file dominates")
+            .changed()
+        {
+            self.save_config();
+        }
+        if ui
+            .checkbox(&mut self.split_cells, "Split cells")
+            .on_hover_text("Check each whitespace- or comma-separated piece of a URL field as its own URL")
+            .changed()
+        {
+            self.save_config();
+            self.refresh_sample_preview();
+        }
+        if ui
+            .checkbox(&mut self.scan_text, "Scan text for URLs")
+            .on_hover_text("Pull every URL out of free text in a field, wherever it appears; may pick up stray links in structured data")
+            .changed()
+        {
+            self.save_config();
+            self.refresh_sample_preview();
+        }
+        if ui.checkbox(&mut self.skip_header, "Skip Header").changed() {
+            self.save_config();
+        }
+        if ui.checkbox(&mut self.continue_on_error, "Continue on Error").changed() {
+            self.save_config();
+        }
+        ui.horizontal(|ui| {
+            ui.label("Read retries:");
+            if ui.add(egui::DragValue::new(&mut self.read_retries).clamp_range(0..=10)).changed() {
+                self.save_config();
+            }
+            ui.label("starting after");
+            if ui
+                .add(egui::DragValue::new(&mut self.read_retry_delay_ms).clamp_range(0..=60_000).suffix(" ms"))
+                .changed()
+            {
+                self.save_config();
+            }
+        });
+        ui.small("Retry opening a file after a transient error such as a network drive timeout; the wait doubles each time");
+        ui.horizontal(|ui| {
+            let mut use_fallback = self.fallback_column.is_some();
+            if ui.checkbox(&mut use_fallback, "Fallback column index:").changed() {
+                self.fallback_column = use_fallback.then_some(0);
+                self.save_config();
+            }
+            if let Some(index) = &mut self.fallback_column {
+                if ui.add(egui::DragValue::new(index).clamp_range(0..=999)).changed() {
+                    self.save_config();
+                }
+            }
+        });
+        ui.small("Read this column (0-based) from files that have none of the selected columns");
+        ui.horizontal(|ui| {
+            let mut use_limit = self.limit.is_some();
+            if ui.checkbox(&mut use_limit, "Stop after unique URLs:").changed() {
+                self.limit = use_limit.then_some(1000);
+                self.save_config();
+            }
+            if let Some(limit) = &mut self.limit {
+                if ui.add(egui::DragValue::new(limit).clamp_range(1..=usize::MAX)).changed() {
+                    self.save_config();
+                }
+            }
+        });
+        if self.limit.is_some() {
+            ui.small("Which URLs are kept varies between runs unless the output is sorted");
+        }
+        ui.horizontal(|ui| {
+            let mut use_min = self.min_url_len.is_some();
+            if ui.checkbox(&mut use_min, "Min URL length:").changed() {
+                self.min_url_len = use_min.then_some(12);
+                self.save_config();
+            }
+            if let Some(min) = &mut self.min_url_len {
+                if ui.add(egui::DragValue::new(min).clamp_range(1..=100_000)).changed() {
+                    self.save_config();
+                }
+            }
+            let mut use_max = self.max_url_len.is_some();
+            if ui.checkbox(&mut use_max, "Max:").changed() {
+                self.max_url_len = use_max.then_some(2048);
+                self.save_config();
+            }
+            if let Some(max) = &mut self.max_url_len {
+                if ui.add(egui::DragValue::new(max).clamp_range(1..=100_000)).changed() {
+                    self.save_config();
+                }
+            }
+        });
+        ui.small("Drop valid URLs with fewer or more characters, e.g. truncated or junk links");
+        ui.horizontal(|ui| {
+            let mut use_cap = self.max_per_domain.is_some();
+            if ui.checkbox(&mut use_cap, "Max URLs per domain:").changed() {
+                self.max_per_domain = use_cap.then_some(100);
+                self.save_config();
+            }
+            if let Some(cap) = &mut self.max_per_domain {
+                if ui.add(egui::DragValue::new(cap).clamp_range(1..=1_000_000)).changed() {
+                    self.save_config();
+                }
+            }
+        });
+        ui.small("Keep one site from dominating the output; the first URLs in output order are kept");
+
+        ui.label("Delimiter:");
+        let mut delimiter = self.delimiter;
+        egui::ComboBox::from_id_source("delimiter_selector")
+            .selected_text(delimiter.label())
+            .show_ui(ui, |ui| {
+                for option in Delimiter::ALL {
+                    ui.selectable_value(&mut delimiter, option, option.label());
+                }
+            });
+        if delimiter != self.delimiter {
+            self.delimiter = delimiter;
+            self.load_sample_csv();
+            self.save_config();
+        }
+
+        ui.label("Encoding:");
+        let mut encoding = self.encoding;
+        egui::ComboBox::from_id_source("encoding_selector")
+            .selected_text(encoding.label())
+            .show_ui(ui, |ui| {
+                for option in InputEncoding::ALL {
+                    ui.selectable_value(&mut encoding, option, option.label());
+                }
+            });
+        if encoding != self.encoding {
+            self.encoding = encoding;
+            self.load_sample_csv();
+            self.save_config();
+        }
+
+        ui.label("Whitespace Trimming:");
+        let mut trim = self.trim;
+        egui::ComboBox::from_id_source("trim_selector")
+            .selected_text(trim.label())
+            .show_ui(ui, |ui| {
+                for option in CsvTrim::ALL {
+                    ui.selectable_value(&mut trim, option, option.label());
+                }
+            });
+        if trim != self.trim {
+            self.trim = trim;
+            self.load_sample_csv();
+            self.save_config();
+        }
+
+        ui.add_space(10.0);
+        if ui.checkbox(&mut self.check_live, "Check links are live").changed() {
+            self.save_config();
+        }
+        if self.check_live {
+            ui.horizontal(|ui| {
+                ui.label("Timeout (s):");
+                if ui.add(egui::DragValue::new(&mut self.timeout_secs).clamp_range(1..=120)).changed() {
+                    self.save_config();
+                }
+            });
+            ui.small("URLs that fail or return 4xx/5xx are written to dead_urls.txt");
+        }
+
+        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            ui.label("Accepted URL schemes:");
+            if ui.text_edit_singleline(&mut self.schemes).changed() {
+                self.save_config();
+            }
+        });
+        ui.small("Comma-separated, e.g. http, https, ftp, mailto");
+
+        ui.add_space(10.0);
+        if ui.checkbox(&mut self.normalize, "Normalize URLs before deduplication").changed() {
+            self.save_config();
+        }
+        if self.normalize {
+            ui.label("Strip query parameters:");
+            if ui.text_edit_singleline(&mut self.tracking_params).changed() {
+                self.save_config();
+            }
+            ui.small("Comma-separated; a trailing * matches a prefix, e.g. utm_*");
+        }
+        ui.horizontal(|ui| {
+            if ui.checkbox(&mut self.strip_query, "Strip query strings").changed() {
+                self.save_config();
+            }
+            if ui.checkbox(&mut self.strip_fragment, "Strip fragments").changed() {
+                self.save_config();
+            }
+        });
+
+        ui.add_space(10.0);
+        ui.label("Exclude Patterns (one regex per line):");
+        if ui.add(TextEdit::multiline(&mut self.exclude_patterns).desired_rows(3)).changed() {
+            self.save_config();
+        }
+
+        ui.add_space(10.0);
+        ui.label("Include Domains (one per line, empty = all):");
+        if ui.add(TextEdit::multiline(&mut self.include_domains).desired_rows(3)).changed() {
+            self.save_config();
+        }
+        ui.label("Exclude Domains (one per line):");
+        if ui.add(TextEdit::multiline(&mut self.exclude_domains).desired_rows(3)).changed() {
+            self.save_config();
+        }
+        ui.small("Subdomains match too: indeed.com covers uk.indeed.com");
+
+        ui.add_space(10.0);
+        ui.label("Rewrite Rules (find → replace):");
+        let mut rules_changed = false;
+        let mut remove = None;
+        for (i, rule) in self.rewrite_rules.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                rules_changed |= ui.checkbox(&mut rule.enabled, "").changed();
+                rules_changed |= ui.add(TextEdit::singleline(&mut rule.find).desired_width(180.0)).changed();
+                ui.label("→");
+                rules_changed |= ui.add(TextEdit::singleline(&mut rule.replace).desired_width(180.0)).changed();
+                if ui.small_button("✖").on_hover_text("Remove rule").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            self.rewrite_rules.remove(i);
+            rules_changed = true;
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Add rule").clicked() {
+                self.rewrite_rules.push(RewriteRule {
+                    find: String::new(),
+                    replace: String::new(),
+                    enabled: true,
+                });
+                rules_changed = true;
+            }
+            if ui.button("Restore defaults").on_hover_text("Add back any shipped rules that were removed").clicked() {
+                for rule in RewriteRule::defaults() {
+                    if !self.rewrite_rules.iter().any(|r| r.find == rule.find && r.replace == rule.replace) {
+                        self.rewrite_rules.push(rule);
+                    }
+                }
+                rules_changed = true;
+            }
+        });
+        if rules_changed {
+            self.save_config();
+        }
+
+        ui.add_space(10.0);
+        self.render_url_test(ui);
+
+        ui.add_space(10.0);
+        ui.label("Duplicate Matching:");
+        let mut dedup_mode = self.dedup_mode;
+        egui::ComboBox::from_id_source("dedup_mode_selector")
+            .selected_text(dedup_mode.label())
+            .show_ui(ui, |ui| {
+                for option in DedupMode::ALL {
+                    ui.selectable_value(&mut dedup_mode, option, option.label());
+                }
+            });
+        if dedup_mode != self.dedup_mode {
+            self.dedup_mode = dedup_mode;
+            Arc::make_mut(&mut self.master_list).set_dedup_mode(dedup_mode);
+            self.save_config();
+        }
+        if self.dedup_mode == DedupMode::FullyNormalized {
+            ui.small("Also ignores path case, which some servers treat as significant");
+        }
+
+        ui.add_space(10.0);
+        ui.label("Dedup Regex:");
+        if ui.text_edit_singleline(&mut self.dedup_regex).changed() {
+            self.save_config();
+        }
+        match compile_dedup_regex(&self.dedup_regex) {
+            Ok(Some(_)) => {
+                ui.small("URLs with the same `key` capture are treated as duplicates");
+            }
+            Ok(None) => {
+                ui.small(r"Example: linkedin\.com/jobs/view/(?P<key>\d+)");
+            }
+            Err(e) => {
+                ui.colored_label(egui::Color32::from_rgb(220, 80, 80), e);
+            }
+        }
+
+        ui.add_space(10.0);
+        if ui.checkbox(&mut self.write_excluded, "Write excluded URLs").changed() {
+            self.save_config();
+        }
+        if self.write_excluded {
+            ui.small("Written to <output>.excluded.txt with the reason each URL was dropped");
+        }
+        if ui.checkbox(&mut self.write_invalid, "Write invalid URLs").changed() {
+            self.save_config();
+        }
+        if self.write_invalid {
+            ui.small("Fields that aren't valid URLs are written to invalid_urls.txt with their source file");
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Chart time budget (s):");
+            if ui.add(egui::DragValue::new(&mut self.chart_timeout_secs).clamp_range(1..=600)).changed() {
+                self.save_config();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Sessions kept in history:");
+            if ui.add(egui::DragValue::new(&mut self.max_history_sessions).clamp_range(1..=100_000)).changed() {
+                self.save_config();
+            }
+        });
+
+        if ui.checkbox(&mut self.report_high_volume, "Report high-volume domains").changed() {
+            self.save_config();
+        }
+        if self.report_high_volume {
+            ui.horizontal(|ui| {
+                ui.label("More than");
+                if ui.add(egui::DragValue::new(&mut self.high_volume_threshold).clamp_range(1..=10_000)).changed() {
+                    self.save_config();
+                }
+                ui.label("distinct URLs per domain");
+            });
+            ui.small("Written to <output>.high_volume.txt");
+        }
+        if ui.checkbox(&mut self.group_by_domain, "Write URLs grouped by domain").changed() {
+            self.save_config();
+        }
+        if self.group_by_domain {
+            ui.small("Written to <output>.by_domain.txt, one section per domain, most URLs first");
+        }
+
+        ui.add_space(10.0);
+        ui.label("Master List File:");
+        ui.horizontal(|ui| {
+            if ui.text_edit_singleline(&mut self.master_list_path).changed() {
+                if Path::new(&self.master_list_path).exists() {
+                    if let Err(e) = Arc::make_mut(&mut self.master_list).load_from_file(&self.master_list_path) {
+                        log::error!("Error loading master list: {}", e);
+                    }
+                }
+                self.save_config();
+            }
+            if ui.button("Browse…").clicked() {
+                if let Some(file) = self.browse(&self.master_list_path, Browse::OpenFile).pop() {
+                    self.master_list_path = file.to_string_lossy().into_owned();
+                    if let Err(e) = Arc::make_mut(&mut self.master_list).load_from_file(&file) {
+                        self.status_message = format!("Error loading master list: {}", e);
+                    }
+                    self.save_config();
+                }
+            }
+            if ui.button("Create new…").on_hover_text("Create an empty master list").clicked() {
+                let path = self.starter_path(&self.master_list_path, "master_list.txt");
+                match create_starter_file(&path).and_then(|_| Arc::make_mut(&mut self.master_list).load_from_file(&path)) {
+                    Ok(()) => {
+                        self.master_list_path = path.to_string_lossy().into_owned();
+                        self.status_message = format!("Master list ready at {}", path.display());
+                        self.save_config();
+                    }
+                    Err(e) => self.status_message = format!("Error creating master list: {}", e),
+                }
+            }
+            if ui
+                .add_enabled(self.master_list.is_loaded(), egui::Button::new("Merge…"))
+                .on_hover_text("Add the entries of another master list to this one")
+                .on_disabled_hover_text("Load a master list first")
+                .clicked()
+            {
+                if let Some(file) = self.browse(&self.master_list_path, Browse::OpenFile).pop() {
+                    self.merge_master_list(&file);
+                }
+            }
+        });
+
+        ui.add_space(10.0);
+        ui.label("Statistics Directory (charts and reports):");
+        ui.horizontal(|ui| {
+            if ui.text_edit_singleline(&mut self.statistics_dir).changed() {
+                self.save_config();
+            }
+            if ui.button("Browse…").clicked() {
+                if let Some(dir) = self.browse(&self.statistics_dir, Browse::Folder).pop() {
+                    self.statistics_dir = dir.to_string_lossy().into_owned();
+                    self.save_config();
+                }
+            }
+        });
+
+        if self.master_list.is_loaded() {
+            ui.label("Master list is loaded and will filter processed URLs");
+        }
+        ui.horizontal(|ui| {
+            ui.label("Prune entries older than");
+            if ui.add(egui::Slider::new(&mut self.prune_days, 1..=730).suffix(" days")).changed() {
+                self.save_config();
+            }
+            if ui.button("Prune now").clicked() {
+                self.prune_master_list();
+            }
+        });
+        ui.small("Entries from older lists without a first-seen time are never pruned");
+        ui.horizontal(|ui| {
+            ui.label("Backups kept:");
+            if ui.add(egui::DragValue::new(&mut self.master_list_backups).clamp_range(0..=100)).changed() {
+                Arc::make_mut(&mut self.master_list).set_max_backups(self.master_list_backups);
+                self.save_config();
+            }
+        });
+
+        ui.add_space(10.0);
+        ui.label("Sample CSV:");
+        ui.horizontal(|ui| {
+            if ui.text_edit_singleline(&mut self.sample_file_path).changed() {
+                if Path::new(&self.sample_file_path).exists() {
+                    self.load_sample_csv();
+                }
+                self.save_config();
+            }
+            if ui.button("Browse…").clicked() {
+                if let Some(file) = self.browse(&self.sample_file_path, Browse::OpenFile).pop() {
+                    self.sample_file_path = file.to_string_lossy().into_owned();
+                    self.load_sample_csv();
+                    self.save_config();
+                }
+            }
+        });
+    }
+
+    fn save(&mut self, _storage: &mut dyn Storage) {
+        self.write_config();
+    }
+}
+
+impl App for ExportCsvLinksApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
+        self.update(ctx, _frame);
+    }
+
+    fn save(&mut self, _storage: &mut dyn Storage) { // Added underscore to unused parameter
+        self.write_config();
+    }
+}
+
+impl Drop for ExportCsvLinksApp {
+    /// Writes settings changed within the last save interval, and lets the
+    /// statistics files finish, before the window closes.
+    fn drop(&mut self) {
+        if self.save_pending {
+            self.write_config();
+        }
+        if let Some(writer) = self.stats_writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+/// Opens `path` with the platform's default handler: the associated
+/// application for a file, the file manager for a directory.
+fn system_open(path: &Path) -> std::io::Result<()> {
+    let program = if cfg!(target_os = "windows") {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    std::process::Command::new(program).arg(path).spawn().map(|_| ())
+}
+
+/// Opens the directory `path` in the platform's file manager, resolving it
+/// against the working directory first.
+fn open_in_file_manager(path: &Path) -> std::io::Result<()> {
+    system_open(&std::path::absolute(path)?)
+}
+
+/// Shows `path` in the file manager, selected where the platform allows it.
+fn reveal_in_file_manager(path: &Path) -> std::io::Result<()> {
+    // `absolute` rather than `canonicalize`, which yields `\\?\` paths explorer rejects
+    let path = std::path::absolute(path)?;
+    if cfg!(target_os = "windows") {
+        let mut select = std::ffi::OsString::from("/select,");
+        select.push(&path);
+        std::process::Command::new("explorer").arg(select).spawn().map(|_| ())
+    } else if cfg!(target_os = "macos") {
+        std::process::Command::new("open").arg("-R").arg(&path).spawn().map(|_| ())
+    } else {
+        open_in_file_manager(path.parent().unwrap_or(&path))
+    }
+}
+
+/// The header scan table: each file with whether it has every selected
+/// column; hovering a file lists its headers.
+fn show_header_scan(ui: &mut egui::Ui, scan: &Result<Vec<FileHeaders>, String>) {
+    let pass_color = egui::Color32::from_rgb(80, 180, 80);
+    let fail_color = egui::Color32::from_rgb(220, 80, 80);
+    let files = match scan {
+        Ok(files) => files,
+        Err(e) => {
+            ui.colored_label(fail_color, e);
+            return;
+        }
+    };
+    let complete = files.iter().filter(|file| file.headers.is_ok() && file.missing.is_empty()).count();
+    egui::CollapsingHeader::new(format!("Header Scan ({} of {} files have every column)", complete, files.len()))
+        .default_open(true)
+        .show(ui, |ui| {
+            egui::ScrollArea::vertical()
+                .id_source("header_scan")
+                .max_height(160.0)
+                .show(ui, |ui| {
+                    egui::Grid::new("header_scan_grid").num_columns(2).striped(true).show(ui, |ui| {
+                        for file in files {
+                            match &file.headers {
+                                Ok(headers) => {
+                                    ui.label(&file.file).on_hover_text(headers.join(", "));
+                                    if file.missing.is_empty() {
+                                        ui.colored_label(pass_color, "✔ has every column");
+                                    } else {
+                                        ui.colored_label(fail_color, format!("✖ missing {}", file.missing.join(", ")));
+                                    }
+                                }
+                                Err(e) => {
+                                    ui.label(&file.file);
+                                    ui.colored_label(fail_color, format!("✖ {}", e));
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+        });
+}
+
+/// Opens the main window.
+pub fn run() -> Result<(), eframe::Error> {
+    // The window's size and position are kept in the config file rather than
+    // eframe's storage, which needs its `persistence` feature
+    let config = AppConfig::load();
+    let mut viewport = egui::ViewportBuilder::default()
+        .with_inner_size(egui::Vec2::from(config.window_size))
+        .with_min_inner_size(egui::vec2(400.0, 500.0))
+        .with_resizable(true);
+    if let Some(pos) = config.window_pos {
+        viewport = viewport.with_position(egui::Pos2::from(pos));
+    }
+    let options = NativeOptions {
+        viewport,
+        ..Default::default()
+    };
+    
+    eframe::run_native(
+        "Export CSV Links",
+        options,
+        Box::new(|_cc| Box::new(ExportCsvLinksApp::default())),
+    )
+}
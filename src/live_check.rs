@@ -0,0 +1,33 @@
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+use std::time::Duration;
+
+/// Issues a HEAD request to each URL and splits them into `(live, dead)`.
+///
+/// A URL is dead when the request fails, times out, or returns a 4xx/5xx
+/// status. Servers that reject HEAD with 405 get a GET instead.
+pub fn partition_live<'a>(
+    urls: &[&'a String],
+    workers: usize,
+    timeout: Duration,
+) -> Result<(Vec<&'a String>, Vec<&'a String>), Box<dyn std::error::Error>> {
+    let client = Client::builder().timeout(timeout).build()?;
+    let pool = ThreadPoolBuilder::new().num_threads(workers).build()?;
+
+    Ok(pool.install(|| urls.par_iter().copied().partition(|url| is_live(&client, url))))
+}
+
+fn is_live(client: &Client, url: &str) -> bool {
+    let status = match client.head(url).send() {
+        Ok(response) if response.status() == StatusCode::METHOD_NOT_ALLOWED => {
+            client.get(url).send().map(|r| r.status())
+        }
+        other => other.map(|r| r.status()),
+    };
+    match status {
+        Ok(status) => !(status.is_client_error() || status.is_server_error()),
+        Err(_) => false,
+    }
+}
@@ -1,743 +1,1864 @@
+// Settings and helpers that only the GUI reaches are unused in CLI-only builds
+#![cfg_attr(not(feature = "gui"), allow(dead_code))]
+
 use clap::Parser;
 use csv::StringRecord;
 use once_cell::sync::Lazy;
+use rayon::prelude::*;
 use rayon::ThreadPoolBuilder;
 use regex::Regex;
-use std::collections::HashSet;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs::{self, File};
-use std::io::{BufWriter, Write};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
-use eframe::{egui, App, Frame, NativeOptions, Storage};
-use egui::{CentralPanel, TextEdit, TopBottomPanel};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
 use chrono::Local;
 mod master_list;
 use master_list::MasterList;
 mod app_config;
-use app_config::{AppConfig, Statistics};
+use app_config::{AppConfig, ChartFormat, CsvTrim, DedupMode, Delimiter, InputEncoding, LineEnding, OutputFormat, SortOrder, Statistics};
 mod enhanced_stats;
-use enhanced_stats::{EnhancedStatistics, ProcessingSession};
+use enhanced_stats::{high_volume_domains, EnhancedStatistics};
+mod url_utils;
+mod sources;
+mod live_check;
+use live_check::partition_live;
+mod exclusions;
+use exclusions::Exclusions;
+mod output;
+mod cli;
+use cli::CliError;
+mod streaming;
+use streaming::StreamingWriter;
+#[cfg(feature = "gui")]
+mod charts;
+#[cfg(feature = "gui")]
+mod gui;
+use output::{read_output_urls, read_url_file, sort_urls, write_records, OutputRecord};
+use sources::{collect_csv_sources, decode_reader, detect_delimiter, CsvSource, FileFilter, COMPRESSED_EXTENSIONS};
+use url_utils::{clean_host, dedup_form, dedup_key, is_valid_url, normalize_url, strip_url_parts, DomainFilter};
+
+/// Exit statuses of a command-line run, shown at the end of `--help`.
+const EXIT_CODES_HELP: &str = "\
+Exit codes:
+  0  Success
+  1  Error: unreadable directory, unwritable output, invalid settings
+  2  Invalid command-line arguments
+  3  No CSV files found in the directory
+  4  No URLs extracted (with --fail-on-empty)
+  5  Some files had errors (without --continue-on-error)";
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, after_help = EXIT_CODES_HELP)]
 struct Args {
-    /// Path to the directory containing CSV files
-    directory: PathBuf,
+    /// Directory containing CSV files; runs headlessly when given, otherwise opens the GUI
+    directory: Option<PathBuf>,
 
-    /// Path to the output text file (default: all_urls.txt)
+    /// Path to the output text file, or `-` for standard output (default: all_urls.txt)
     #[arg(short, long, default_value = "all_urls.txt")]
     output: PathBuf,
 
-    /// Skip the first record of data (not the header row) in CSV files
+    /// Order of URLs in the output; `none` is fastest but differs between runs
+    #[arg(long, value_enum, default_value_t = SortOrder::Alpha)]
+    sort: SortOrder,
+
+    /// Append to the output file instead of overwriting it, skipping URLs it
+    /// already contains (text and CSV output only)
+    #[arg(long)]
+    append: bool,
+
+    /// Record every source file per URL; text output becomes `url<TAB>file` lines
+    #[arg(long)]
+    with_source: bool,
+
+    /// Output format: one URL per line, a JSON array, or CSV with source file and domain
+    #[arg(long, value_enum, default_value_t = OutputFormat::Txt)]
+    format: OutputFormat,
+
+    /// Line separator for the output file
+    #[arg(long, value_enum, default_value_t = LineEnding::Lf)]
+    eol: LineEnding,
+
+    /// Column name to extract URLs from (repeat to read several columns)
+    #[arg(long = "header", default_value = "Company Apply Url")]
+    headers: Vec<String>,
+
+    /// Skip the first record of data (not the header row) in CSV files; with
+    /// --no-header this drops the first line of each file
     #[arg(short, long)]
     skip_header: bool,
 
-    /// Number of worker threads for concurrent processing (default: 4)
-    #[arg(short, long, default_value_t = 4)]
+    /// The CSV files have no header row; every line is data, so columns must
+    /// be given by --column index
+    #[arg(long, requires = "columns")]
+    no_header: bool,
+
+    /// Number of worker threads for concurrent processing; 0 uses every core
+    #[arg(short, long, default_value_t = 0)]
     workers: usize,
 
-    /// Path to a file containing URLs to exclude (one URL per line)
+    /// Path to a file containing URLs to exclude (one URL per line; repeatable)
+    #[arg(long)]
+    exclude_file: Vec<PathBuf>,
+
+    /// Regex; URLs matching it anywhere are excluded (repeatable)
     #[arg(long)]
-    exclude_file: Option<PathBuf>,
+    exclude_pattern: Vec<String>,
 
     /// Continue processing even if some files produce errors
     #[arg(long, default_value_t = false)]
     continue_on_error: bool,
+
+    /// Exit with status 4 when no URLs were extracted
+    #[arg(long)]
+    fail_on_empty: bool,
+
+    /// Times to retry opening a file or reading its header row after a
+    /// transient I/O error (timeouts, dropped network shares)
+    #[arg(long, default_value_t = 2)]
+    read_retries: u32,
+
+    /// Milliseconds to wait before the first read retry; doubles each retry
+    #[arg(long, default_value_t = 250)]
+    read_retry_delay: u64,
+
+    /// Also scan subdirectories of the input directory for CSV files
+    #[arg(short, long)]
+    recursive: bool,
+
+    /// How URLs are compared for duplicates and against the master list
+    #[arg(long, value_enum, default_value_t = DedupMode::CaseInsensitiveHost)]
+    dedup_mode: DedupMode,
+
+    /// Regex with a named `key` group; URLs with the same captured key are duplicates
+    /// (e.g. `linkedin\.com/jobs/view/(?P<key>\d+)`)
+    #[arg(long)]
+    dedup_regex: Option<String>,
+
+    /// After extraction, drop URLs that fail a HEAD request (written to dead_urls.txt)
+    #[arg(long)]
+    check_live: bool,
+
+    /// Timeout in seconds for each live-check request
+    #[arg(long, default_value_t = 10)]
+    timeout: u64,
+
+    /// Suppress the end-of-run summary table and every message but errors
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Print more detail: -v adds per-file counts, -vv debugging output
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Field delimiter; `auto` reads `.tsv` files as tab-separated and
+    /// detects it from the first line of anything else
+    #[arg(long, value_enum, default_value_t = Delimiter::Auto)]
+    delimiter: Delimiter,
+
+    /// Text encoding of the CSV files; `auto` honours a BOM and falls back to Windows-1252
+    #[arg(long, value_enum, default_value_t = InputEncoding::Auto)]
+    encoding: InputEncoding,
+
+    /// Whitespace trimming applied by the CSV reader to headers and/or fields
+    #[arg(long, value_enum, default_value_t = CsvTrim::All)]
+    trim: CsvTrim,
+
+    /// Seconds each statistics chart may take to render before it is skipped
+    #[arg(long, default_value_t = 30)]
+    chart_timeout: u64,
+
+    /// Width in pixels of the domain chart (default from Settings, 1600); the
+    /// trend chart is half as wide
+    #[arg(long)]
+    chart_width: Option<u32>,
+
+    /// Height in pixels of the domain chart (default from Settings, 900); the
+    /// trend chart is two thirds as tall
+    #[arg(long)]
+    chart_height: Option<u32>,
+
+    /// Image format of the statistics charts (default from Settings, png)
+    #[arg(long, value_enum)]
+    chart_format: Option<ChartFormat>,
+
+    /// Normalize URLs before deduplication (lowercase host, no default port or
+    /// trailing slash, tracking parameters removed)
+    #[arg(long)]
+    normalize: bool,
+
+    /// Query parameter removed by --normalize; a trailing `*` matches a prefix (repeatable)
+    #[arg(long = "strip-param", default_values_t = [String::from("utm_*"), String::from("fbclid"), String::from("gclid")])]
+    strip_params: Vec<String>,
+
+    /// Remove the query string (`?...`) from every URL before deduplication
+    #[arg(long)]
+    strip_query: bool,
+
+    /// Remove the fragment (`#...`) from every URL before deduplication
+    #[arg(long)]
+    strip_fragment: bool,
+
+    /// Comma-separated URL schemes to accept (e.g. `http,https,ftp,mailto`)
+    #[arg(long, value_delimiter = ',', default_value = "http,https")]
+    schemes: Vec<String>,
+
+    /// Column index (0-based) to read URLs from, instead of matching --header
+    /// names (repeat to read several columns); takes precedence over --header
+    #[arg(long = "column")]
+    columns: Vec<usize>,
+
+    /// Comma-separated file extensions to read, e.g. `csv,tsv,txt`; also
+    /// read compressed as `.csv.gz`, `.csv.bz2` or `.csv.xz`
+    #[arg(long, value_delimiter = ',', default_value = "csv")]
+    extensions: Vec<String>,
+
+    /// Only read CSV files whose name matches this glob, e.g. `export_*.csv`
+    /// (repeatable; case-insensitive)
+    #[arg(long = "pattern")]
+    patterns: Vec<String>,
+
+    /// Skip CSV files whose name matches this glob, e.g. `template*.csv` (repeatable)
+    #[arg(long = "skip-pattern")]
+    skip_patterns: Vec<String>,
+
+    /// Stop once this many unique URLs not already in the master list have
+    /// been collected (before exclusions); which ones are kept varies between
+    /// runs with several workers
+    #[arg(long)]
+    limit: Option<usize>,
+
+    /// Drop valid URLs shorter than this many characters
+    #[arg(long)]
+    min_url_len: Option<usize>,
+
+    /// Drop valid URLs longer than this many characters
+    #[arg(long)]
+    max_url_len: Option<usize>,
+
+    /// Column index (0-based) to read from files that have none of the --header columns
+    #[arg(long)]
+    fallback_column: Option<usize>,
+
+    /// Replace FIND with REPLACE in every extracted URL (repeatable), e.g.
+    /// `--rewrite linkedin.com/job-apply/ linkedin.com/jobs/view/`
+    #[arg(long, num_args = 2, value_names = ["FIND", "REPLACE"])]
+    rewrite: Vec<String>,
+
+    /// Only keep URLs on this domain or its subdomains (repeatable)
+    #[arg(long = "include-domain")]
+    include_domains: Vec<String>,
+
+    /// Drop URLs on this domain or its subdomains (repeatable)
+    #[arg(long = "exclude-domain")]
+    exclude_domains: Vec<String>,
+
+    /// Append a tab-separated first-seen timestamp to each output URL
+    #[arg(long)]
+    first_seen: bool,
+
+    /// Compare this run's output with a previous output file or master list,
+    /// writing the differences to new_urls.txt and removed_urls.txt
+    #[arg(long, value_name = "FILE", conflicts_with = "streaming")]
+    diff_against: Option<PathBuf>,
+
+    /// Run extraction and filtering and print the summary without writing
+    /// the output file or updating the master list
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Master list file; URLs already in it are skipped and new ones are added
+    #[arg(long)]
+    master_list: Option<PathBuf>,
+
+    /// Before writing output, drop master list entries first seen more than this many days ago
+    #[arg(long)]
+    prune_days: Option<u64>,
+
+    /// Also split each file's records into chunks checked across the worker
+    /// pool; helps when one huge file dominates the run
+    #[arg(long)]
+    parallel_records: bool,
+
+    /// Split each URL field on whitespace and commas and check every piece
+    /// as its own URL, for cells holding several links
+    #[arg(long)]
+    split_cells: bool,
+
+    /// Pull every URL out of free text wherever it appears in a field
+    /// ("Apply at https://... today!"); takes precedence over --split-cells
+    #[arg(long)]
+    scan_text: bool,
+
+    /// Write URLs to the output as each file is read instead of holding the
+    /// whole run in memory. Keeps memory flat on very large inputs, but the
+    /// output is unsorted, --check-live, --count-occurrences, --write-excluded
+    /// and --high-volume-threshold are ignored, and JSON output is not supported
+    #[arg(long)]
+    streaming: bool,
+
+    /// Write URLs dropped by the exclude list or the master list, or found
+    /// dead, to <output>.excluded.txt with the reason
+    #[arg(long)]
+    write_excluded: bool,
+
+    /// Add a tab-separated count of how often each URL appeared across all
+    /// files and sort the output by it, most frequent first
+    #[arg(long)]
+    count_occurrences: bool,
+
+    /// Directory for charts and reports (default: the configured statistics directory)
+    #[arg(long)]
+    statistics_dir: Option<PathBuf>,
+
+    /// Write fields that aren't valid URLs to invalid_urls.txt, next to the output
+    #[arg(long)]
+    write_invalid: bool,
+
+    /// Write registrable domains with more than this many distinct URLs to <output>.high_volume.txt
+    #[arg(long)]
+    high_volume_threshold: Option<usize>,
+
+    /// Also write the output URLs grouped by host to <output>.by_domain.txt,
+    /// one section per domain, most URLs first
+    #[arg(long, conflicts_with = "streaming")]
+    group_by_domain: bool,
+
+    /// Write at most this many URLs per host (ignoring `www.`), keeping the
+    /// first ones in output order
+    #[arg(long, value_name = "N")]
+    max_per_domain: Option<usize>,
+
+    /// Config file to load and save instead of the default one (also set by
+    /// the CSV_LINK_EXTRACTOR_CONFIG environment variable)
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Use the settings saved in this profile; the GUI opens with it active,
+    /// and command-line runs take the statistics directory and chart settings
+    /// from it
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Print each CSV file's name and header row, marking files that lack a
+    /// --header column, and exit without extracting anything
+    #[arg(long, requires = "directory")]
+    list_headers: bool,
+
+    /// Print this build's features and the effective configuration (worker
+    /// count, config and statistics paths, saved settings) as JSON and exit
+    #[arg(long)]
+    capabilities: bool,
 }
 
-// Compile the URL validation regex once
-static URL_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(
-        r"(?i)^http[s]?://(?:[a-zA-Z0-9\$\-_@.&+!*\(\),]|(?:%[0-9a-fA-F]{2}))+"
-    )
-    .expect("Invalid regex")
-});
+/// Enhanced statistics history, stored next to the config file.
+const HISTORY_FILE: &str = "statistics_history.json";
+
+/// Sample rows scanned by [`detect_url_column`].
+const URL_COLUMN_SAMPLE_ROWS: usize = 50;
+
+/// Guesses which column holds URLs: the one whose non-empty fields in
+/// `records` most often pass `is_valid_url`. Ties go to the leftmost column;
+/// `None` when no field is a valid URL.
+fn detect_url_column(records: &[StringRecord], schemes: &[String]) -> Option<usize> {
+    let columns = records.iter().map(StringRecord::len).max().unwrap_or(0);
+    let mut best: Option<(usize, f64)> = None;
+    for column in 0..columns {
+        let fields: Vec<&str> = records
+            .iter()
+            .filter_map(|record| record.get(column))
+            .map(str::trim)
+            .filter(|field| !field.is_empty())
+            .collect();
+        let valid = fields.iter().filter(|field| is_valid_url(field, schemes)).count();
+        if valid == 0 {
+            continue;
+        }
+        let ratio = valid as f64 / fields.len() as f64;
+        if best.is_none_or(|(_, best_ratio)| ratio > best_ratio) {
+            best = Some((column, ratio));
+        }
+    }
+    best.map(|(column, _)| column)
+}
+
+/// Cores available to this process, or 1 when that can't be determined.
+fn available_workers() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
 
-fn is_valid_url(url: &str) -> bool {
-    URL_REGEX.is_match(url)
+/// The worker count to use for a requested one: 0 means every available core,
+/// and more workers than cores are allowed but warned about.
+fn resolve_workers(requested: usize) -> usize {
+    let available = available_workers();
+    if requested == 0 {
+        return available;
+    }
+    if requested > available {
+        log::warn!(
+            "{} workers requested but only {} cores are available; extra workers mostly add overhead",
+            requested,
+            available
+        );
+    }
+    requested
 }
 
-fn extract_urls_from_csv(
-    csv_filepath: &PathBuf,
+/// Whether `path` is `-`, meaning the output goes to standard output.
+fn is_stdout(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+/// Opens the output for writing: standard output for `-`, otherwise the file,
+/// appended to or truncated.
+fn open_output(path: &Path, append: bool) -> std::io::Result<Box<dyn Write + Send>> {
+    if is_stdout(path) {
+        return Ok(Box::new(BufWriter::new(std::io::stdout())));
+    }
+    let file = if append {
+        fs::OpenOptions::new().create(true).append(true).open(path)?
+    } else {
+        File::create(path)?
+    };
+    Ok(Box::new(BufWriter::new(file)))
+}
+
+/// Whether `a` and `b` name the same file, compared as canonical paths so
+/// `./out.txt` and `out.txt` match. A file that doesn't exist yet is
+/// compared through its parent directory.
+fn same_file(a: &Path, b: &Path) -> bool {
+    fn canonical(path: &Path) -> PathBuf {
+        fs::canonicalize(path).unwrap_or_else(|_| match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => {
+                let parent = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+                fs::canonicalize(parent)
+                    .map(|parent| parent.join(name))
+                    .unwrap_or_else(|_| path.to_path_buf())
+            }
+            _ => path.to_path_buf(),
+        })
+    }
+    canonical(a) == canonical(b)
+}
+
+/// Builds a companion file path next to `output`, e.g. `all_links.txt` -> `all_links.<suffix>`.
+fn companion_path(output: &Path, suffix: &str) -> PathBuf {
+    let stem = output
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .filter(|_| !is_stdout(output))
+        .unwrap_or("output");
+    output.with_file_name(format!("{}.{}", stem, suffix))
+}
+
+fn write_high_volume_report(path: &Path, domains: &[(String, usize)]) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    for (domain, count) in domains {
+        writeln!(writer, "{}\t{}", domain, count)?;
+    }
+    writer.flush()
+}
+
+/// Writes `url<TAB>reason` lines for URLs dropped by the exclude list or master list.
+fn write_excluded_report(path: &Path, dropped: &[(&String, &str)]) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    for (url, reason) in dropped {
+        writeln!(writer, "{}\t{}", url, reason)?;
+    }
+    writer.flush()
+}
+
+/// Writes `urls` grouped by host (ignoring `www.`): a `host (count)` header
+/// line followed by its URLs, hosts with the most URLs first (ties by name)
+/// and a blank line between sections. URLs keep their order within a host.
+fn write_domain_groups(path: &Path, urls: &[&String]) -> std::io::Result<()> {
+    let mut groups: HashMap<String, Vec<&str>> = HashMap::new();
+    for url in urls {
+        let host = clean_host(url).unwrap_or_else(|| String::from("(no host)"));
+        groups.entry(host).or_default().push(url.as_str());
+    }
+    let mut groups: Vec<(String, Vec<&str>)> = groups.into_iter().collect();
+    groups.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(&b.0)));
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    for (i, (host, urls)) in groups.iter().enumerate() {
+        if i > 0 {
+            writeln!(writer)?;
+        }
+        writeln!(writer, "{} ({})", host, urls.len())?;
+        for url in urls {
+            writeln!(writer, "{}", url)?;
+        }
+    }
+    writer.flush()
+}
+
+/// Writes one URL per line.
+fn write_url_list<'a>(path: &Path, urls: impl IntoIterator<Item = &'a str>) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    for url in urls {
+        writeln!(writer, "{}", url)?;
+    }
+    writer.flush()
+}
+
+/// Writes every field rejected as an invalid URL as `file<TAB>value` lines.
+fn write_invalid_report(path: &Path, reports: &[FileReport]) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    for report in reports {
+        for value in &report.invalid_urls {
+            writeln!(writer, "{}\t{}", report.file, value)?;
+        }
+    }
+    writer.flush()
+}
+
+/// Checks an output file against the master list and returns output URLs that
+/// only match a master list entry after canonicalization. Exact matches are
+/// expected, since every written URL is added to the master list.
+fn verify_output(output_path: &Path, master_list: &MasterList) -> std::io::Result<Vec<(String, String)>> {
+    let contents = fs::read_to_string(output_path)?;
+    // Output lines may carry extra tab-separated columns after the URL
+    let urls = contents
+        .lines()
+        .map(|line| line.split('\t').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty());
+    Ok(master_list.find_normalization_mismatches(urls))
+}
+
+/// Creates an empty file at `path` (and its parent directories) unless one already exists.
+fn create_starter_file(path: &Path) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    match fs::OpenOptions::new().write(true).create_new(true).open(path) {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Options controlling how URLs are read out of each CSV file and deduplicated.
+#[derive(Clone)]
+struct ExtractOptions {
     skip_header: bool,
+    /// Files have no header row, so only `columns` can select URL fields
+    no_header: bool,
     continue_on_error: bool,
-    header_name: &str,
-) -> Vec<String> {
-    let mut urls = Vec::new();
-    let file = match File::open(csv_filepath) {
-        Ok(f) => f,
-        Err(e) => {
-            eprintln!("Error opening CSV file {:?}: {}", csv_filepath, e);
-            return urls;
-        }
-    };
+    /// Retries after a transient error opening a file or reading its headers
+    read_retries: u32,
+    /// Wait before the first retry in milliseconds, doubled for each one after
+    read_retry_delay_ms: u64,
+    header_names: Vec<String>,
+    /// Column indices to read; when non-empty, `header_names` is ignored
+    columns: Vec<usize>,
+    trim: CsvTrim,
+    delimiter: Delimiter,
+    encoding: InputEncoding,
+    dedup_regex: Option<Regex>,
+    dedup_mode: DedupMode,
+    normalize: bool,
+    tracking_params: Vec<String>,
+    strip_query: bool,
+    strip_fragment: bool,
+    domain_filter: DomainFilter,
+    /// Enabled `(find, replace)` rewrite rules, applied in order
+    rewrites: Vec<(String, String)>,
+    /// URL schemes accepted as valid, e.g. `http`, `https`, `mailto`
+    schemes: Vec<String>,
+    /// Column index to read when none of `header_names` is found
+    fallback_column: Option<usize>,
+    /// Check each whitespace- or comma-separated piece of a field separately
+    split_cells: bool,
+    /// Check every URL found anywhere in a field's text
+    scan_text: bool,
+    /// Check the records of each file in parallel chunks, not just files in parallel
+    parallel_records: bool,
+    /// Stop reading each file after this many records
+    max_records: Option<usize>,
+    /// Stop the run once this many unique URLs have been collected
+    limit: Option<usize>,
+    /// Valid URLs with fewer characters are dropped
+    min_url_len: Option<usize>,
+    /// Valid URLs with more characters are dropped
+    max_url_len: Option<usize>,
+    /// Which CSV files in the directory are read
+    files: FileFilter,
+}
+
+/// Builds a CSV reader over `input`, decoding it to UTF-8 (dropping any BOM)
+/// and sniffing the delimiter from the first line unless one is forced.
+/// Without `has_headers` the first line is returned as a record.
+fn build_csv_reader<R: Read>(
+    input: R,
+    trim: CsvTrim,
+    delimiter: Delimiter,
+    encoding: InputEncoding,
+    has_headers: bool,
+) -> csv::Reader<impl Read> {
+    let mut input = BufReader::new(decode_reader(input, encoding));
+    let delimiter = delimiter
+        .byte()
+        .unwrap_or_else(|| detect_delimiter(input.fill_buf().unwrap_or_default()));
+    csv::ReaderBuilder::new()
+        .trim(trim.into())
+        .delimiter(delimiter)
+        .has_headers(has_headers)
+        .from_reader(input)
+}
 
-    let mut rdr = csv::Reader::from_reader(file);
-    let headers = match rdr.headers() {
-        Ok(h) => h.clone(),
-        Err(e) => {
-            eprintln!("Error reading headers from {:?}: {}", csv_filepath, e);
-            if !continue_on_error {
-                return urls;
+/// What `extract_urls_from_csv` found in one file.
+#[derive(Default)]
+struct FileExtraction {
+    urls: Vec<String>,
+    /// Non-empty fields rejected by `is_valid_url`, as read
+    invalid_urls: Vec<String>,
+    /// Valid URLs dropped by the length bounds
+    length_filtered: usize,
+    /// Selected columns that couldn't be matched in this file's headers
+    missing_columns: Vec<String>,
+    /// Problems hit while reading the file, in order
+    errors: Vec<String>,
+}
+
+impl FileExtraction {
+    fn error(&mut self, message: String) {
+        log::warn!("{}", message);
+        self.errors.push(message);
+    }
+
+    fn add_fields(&mut self, outcomes: impl IntoIterator<Item = FieldOutcome>) {
+        for outcome in outcomes {
+            match outcome {
+                FieldOutcome::Url(url) => self.urls.push(url),
+                FieldOutcome::Invalid(field) => self.invalid_urls.push(field),
+                FieldOutcome::Filtered => {}
+                FieldOutcome::WrongLength => self.length_filtered += 1,
             }
-            StringRecord::new()
         }
-    };
+    }
+}
 
-    let url_index = match headers.iter().position(|h| h == header_name) {
-        Some(i) => i,
-        None => {
-            eprintln!(
-                "Error: '{}' column not found in file {:?}",
-                header_name, csv_filepath
-            );
-            return urls;
+/// Outcome of one file, shown on the Log tab.
+#[derive(Clone)]
+struct FileReport {
+    file: String,
+    urls_found: usize,
+    invalid_urls: Vec<String>,
+    /// Valid URLs dropped by `--min-url-len`/`--max-url-len`
+    length_filtered: usize,
+    missing_columns: Vec<String>,
+    errors: Vec<String>,
+}
+
+/// Normalized header name used for fuzzy matching: lowercase alphanumerics only.
+fn header_key(header: &str) -> String {
+    header
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Finds `wanted` among `headers`, exactly first and then ignoring case,
+/// whitespace and punctuation (`company_apply_url` matches `Company Apply URL`).
+fn find_header(headers: &StringRecord, wanted: &str) -> Option<usize> {
+    headers.iter().position(|h| h == wanted).or_else(|| {
+        let key = header_key(wanted);
+        if key.is_empty() {
+            return None;
         }
+        headers.iter().position(|h| header_key(h) == key)
+    })
+}
+
+fn extract_urls_from_csv(source: &CsvSource, options: &ExtractOptions, cancel: &AtomicBool) -> FileExtraction {
+    let source_name = &source.name;
+    let skip_header = options.skip_header;
+    let continue_on_error = options.continue_on_error;
+    let mut extraction = FileExtraction::default();
+    // Opening the file and reading its headers is retried from scratch after
+    // a transient error, since a failed reader can't be resumed
+    let mut attempt = 0;
+    let (mut rdr, headers) = loop {
+        let retry = attempt < options.read_retries && !cancel.load(Ordering::Relaxed);
+        let file = match source.open() {
+            Ok(f) => f,
+            Err(e) if retry && is_transient(e.kind()) => {
+                wait_before_retry(source_name, &e.to_string(), attempt, options);
+                attempt += 1;
+                continue;
+            }
+            Err(e) => {
+                extraction.error(format!("Error opening CSV file {:?}: {}", source_name, e));
+                return extraction;
+            }
+        };
+
+        let delimiter = options.delimiter.for_path(Path::new(&source.name));
+        let mut rdr = build_csv_reader(file, options.trim, delimiter, options.encoding, !options.no_header);
+        let headers = match rdr.headers() {
+            Ok(_) if options.no_header => StringRecord::new(),
+            Ok(h) => h.clone(),
+            Err(e) if retry && matches!(e.kind(), csv::ErrorKind::Io(io) if is_transient(io.kind())) => {
+                wait_before_retry(source_name, &e.to_string(), attempt, options);
+                attempt += 1;
+                continue;
+            }
+            Err(e) => {
+                extraction.error(format!("Error reading headers from {:?}: {}", source_name, e));
+                if !continue_on_error {
+                    return extraction;
+                }
+                StringRecord::new()
+            }
+        };
+        break (rdr, headers);
     };
 
+    let mut url_indices: Vec<usize> = options.columns.clone();
+    let header_names: &[String] = if url_indices.is_empty() { &options.header_names } else { &[] };
+    for header_name in header_names {
+        match find_header(&headers, header_name) {
+            Some(index) => url_indices.push(index),
+            None => {
+                // Only an error when no other column supplies URLs, below
+                log::warn!("'{}' column not found in file {:?}", header_name, source_name);
+                extraction.missing_columns.push(header_name.clone());
+            }
+        }
+    }
+    if url_indices.is_empty() {
+        match options.fallback_column {
+            Some(index) if index < headers.len() => url_indices.push(index),
+            _ => {
+                extraction.error(format!(
+                    "Error: none of the selected columns ({}) found in file {:?}",
+                    header_names.join(", "),
+                    source_name
+                ));
+                return extraction;
+            }
+        }
+    }
+
     let mut records = rdr.records();
     if skip_header {
         records.next();
     }
 
-    for result in records {
+    // Records are parsed in order; with `parallel_records` their fields are
+    // checked a chunk at a time across the worker pool
+    let mut chunk: Vec<StringRecord> = Vec::new();
+    for result in records.take(options.max_records.unwrap_or(usize::MAX)) {
+        if cancel.load(Ordering::Relaxed) {
+            return extraction;
+        }
         let record: StringRecord = match result {
             Ok(rec) => rec,
             Err(e) => {
-                eprintln!("Error reading record in {:?}: {}", csv_filepath, e);
+                extraction.error(format!("Error reading record in {:?}: {}", source_name, e));
                 if !continue_on_error {
-                    return urls;
+                    break;
                 }
                 continue;
             }
         };
 
-        if let Some(url_field) = record.get(url_index) {
-            let trimmed = url_field.trim();
-            if !trimmed.is_empty() {
-                let replaced = trimmed.replace("linkedin.com/job-apply/", "linkedin.com/jobs/view/");
-                if is_valid_url(&replaced) {
-                    urls.push(replaced);
-                }
+        if options.parallel_records {
+            chunk.push(record);
+            if chunk.len() >= RECORD_CHUNK {
+                extraction.add_fields(check_chunk(&chunk, &url_indices, options));
+                chunk.clear();
+            }
+        } else {
+            for url_field in url_indices.iter().filter_map(|&i| record.get(i)) {
+                extraction.add_fields(check_field(url_field, options));
             }
         }
     }
-    urls
+    extraction.add_fields(check_chunk(&chunk, &url_indices, options));
+    extraction
+}
+
+/// I/O errors worth retrying: the file may well read fine a moment later.
+/// A missing file or denied permission won't fix itself.
+fn is_transient(kind: std::io::ErrorKind) -> bool {
+    use std::io::ErrorKind;
+    matches!(
+        kind,
+        ErrorKind::Interrupted
+            | ErrorKind::WouldBlock
+            | ErrorKind::TimedOut
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected
+            | ErrorKind::BrokenPipe
+    )
+}
+
+/// Logs a transient read error and sleeps before retry `attempt + 1`.
+fn wait_before_retry(source_name: &str, error: &str, attempt: u32, options: &ExtractOptions) {
+    let delay = Duration::from_millis(options.read_retry_delay_ms.saturating_mul(1 << attempt.min(16)));
+    log::warn!(
+        "Transient error reading {:?} ({}); retrying in {} ms ({} of {})",
+        source_name,
+        error,
+        delay.as_millis(),
+        attempt + 1,
+        options.read_retries
+    );
+    std::thread::sleep(delay);
+}
+
+/// Records checked together when a file's records are processed in parallel.
+const RECORD_CHUNK: usize = 8192;
+
+/// What became of one non-empty URL field.
+enum FieldOutcome {
+    Url(String),
+    /// Rejected by `is_valid_url`; holds the field as read
+    Invalid(String),
+    /// Valid, but dropped by the domain filter
+    Filtered,
+    /// Valid, but outside the URL length bounds
+    WrongLength,
+}
+
+/// Anything shaped like `scheme:rest` up to whitespace, quotes or angle
+/// brackets; [`scan_urls`] keeps the ones with an accepted scheme.
+static URL_SCAN_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)\b[a-z][a-z0-9+.\-]*:[^\s<>"']+"#).expect("valid URL scan regex"));
+
+/// Sentence punctuation trimmed from the end of a scanned URL.
+const SCAN_TRAILING: &[char] = &['.', ',', ';', ':', '!', '?', ')', ']', '}'];
+
+/// URL candidates anywhere in `text` whose scheme is one of `schemes`.
+fn scan_urls<'a>(text: &'a str, schemes: &'a [String]) -> impl Iterator<Item = &'a str> + 'a {
+    URL_SCAN_REGEX
+        .find_iter(text)
+        .map(|m| m.as_str().trim_end_matches(SCAN_TRAILING))
+        .filter(|candidate| {
+            candidate
+                .split_once(':')
+                .is_some_and(|(scheme, _)| schemes.iter().any(|s| s.eq_ignore_ascii_case(scheme)))
+        })
+}
+
+/// Checks one field: with `scan_text` every URL found in its text, with
+/// `split_cells` each of its whitespace- or comma-separated pieces, and
+/// otherwise the field as a whole. Empty fields yield nothing.
+fn check_field(field: &str, options: &ExtractOptions) -> Vec<FieldOutcome> {
+    let trimmed = field.trim();
+    if trimmed.is_empty() {
+        Vec::new()
+    } else if options.scan_text {
+        let outcomes: Vec<FieldOutcome> =
+            scan_urls(trimmed, &options.schemes).map(|candidate| check_url(candidate, options)).collect();
+        if outcomes.is_empty() {
+            vec![FieldOutcome::Invalid(trimmed.to_string())]
+        } else {
+            outcomes
+        }
+    } else if options.split_cells {
+        trimmed
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|token| !token.is_empty())
+            .map(|token| check_url(token, options))
+            .collect()
+    } else {
+        vec![check_url(trimmed, options)]
+    }
 }
 
+/// Rewrites, validates, filters and (optionally) strips and normalizes one
+/// candidate URL.
+fn check_url(candidate: &str, options: &ExtractOptions) -> FieldOutcome {
+    let replaced = options
+        .rewrites
+        .iter()
+        .fold(candidate.to_string(), |url, (find, replace)| url.replace(find.as_str(), replace));
+    if !is_valid_url(&replaced, &options.schemes) {
+        FieldOutcome::Invalid(candidate.to_string())
+    } else if !options.domain_filter.allows(&replaced) {
+        FieldOutcome::Filtered
+    } else {
+        let url = if options.strip_query || options.strip_fragment {
+            strip_url_parts(&replaced, options.strip_query, options.strip_fragment)
+        } else {
+            replaced
+        };
+        let url = if options.normalize {
+            normalize_url(&url, &options.tracking_params)
+        } else {
+            url
+        };
+        let len = url.chars().count();
+        if options.min_url_len.is_some_and(|min| len < min) || options.max_url_len.is_some_and(|max| len > max) {
+            FieldOutcome::WrongLength
+        } else {
+            FieldOutcome::Url(url)
+        }
+    }
+}
+
+/// Checks the URL fields of `records` across the current rayon pool,
+/// keeping record order.
+fn check_chunk(records: &[StringRecord], url_indices: &[usize], options: &ExtractOptions) -> Vec<FieldOutcome> {
+    records
+        .par_iter()
+        .flat_map_iter(|record| {
+            url_indices
+                .iter()
+                .filter_map(|&i| record.get(i))
+                .flat_map(|field| check_field(field, options))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Number of distinct URLs found in each source file, most productive first.
+fn urls_per_file(sources: &HashMap<String, Vec<String>>) -> Vec<(String, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for file in sources.values().flatten() {
+        *counts.entry(file.as_str()).or_insert(0) += 1;
+    }
+    let mut counts: Vec<(String, usize)> = counts
+        .into_iter()
+        .map(|(file, count)| (file.to_string(), count))
+        .collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+/// Progress of a running extraction, shared between the worker and the UI.
+#[derive(Default)]
+struct Progress {
+    files_done: AtomicUsize,
+    files_total: AtomicUsize,
+    cancel: AtomicBool,
+}
+
+/// URLs collected by `process_directory`.
+struct ProcessingResult {
+    /// Unique URLs after deduplication; exclusions are applied by the caller
+    urls: HashSet<String>,
+    /// Names of the files each URL was read from, in discovery order
+    sources: HashMap<String, Vec<String>>,
+    /// How many times each URL (or a duplicate of it) was read
+    occurrences: HashMap<String, usize>,
+    /// Every valid URL occurrence read, before deduplication
+    total_found: usize,
+    /// `(file, column)` for each selected column a file didn't have
+    missing_columns: Vec<(String, String)>,
+    /// One entry per file processed, sorted by file name
+    file_reports: Vec<FileReport>,
+    /// The run stopped early because `limit` unique URLs were collected
+    limit_reached: bool,
+    /// Unique URLs skipped because the master list already had them
+    known_urls: Vec<String>,
+}
+
+impl ProcessingResult {
+    /// Valid URLs dropped by the length bounds across every file.
+    fn length_filtered(&self) -> usize {
+        self.file_reports.iter().map(|report| report.length_filtered).sum()
+    }
+}
+
+/// The URL kept for one dedup key, with every file it appeared in and how
+/// many times it was read in total.
+struct DedupEntry {
+    url: String,
+    sources: Vec<String>,
+    count: usize,
+    /// Already in the master list: kept only so it's reported once, then
+    /// left out of the result
+    known: bool,
+}
+
+/// The URLs every worker merges into, by dedup key.
+#[derive(Default)]
+struct DedupUrls {
+    entries: HashMap<String, DedupEntry>,
+    /// Entries not already in the master list, the ones `limit` counts
+    new: usize,
+}
+
+/// Locks `mutex`, carrying on with the data if another worker panicked while
+/// holding it rather than failing the whole run.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[allow(clippy::too_many_arguments)]
 fn process_file(
-    csv_filepath: PathBuf,
-    dedup_urls: Arc<Mutex<HashSet<String>>>,
-    skip_header: bool,
-    continue_on_error: bool,
-    header_name: String,
+    source: CsvSource,
+    dedup_urls: Arc<Mutex<DedupUrls>>,
+    total_found: &AtomicUsize,
+    reports: &Mutex<Vec<FileReport>>,
+    options: &ExtractOptions,
+    progress: &Progress,
+    sink: Option<&StreamingWriter>,
+    master_list: Option<&MasterList>,
+    limit_reached: &AtomicBool,
 ) {
-    let urls = extract_urls_from_csv(&csv_filepath, skip_header, continue_on_error, &header_name);
-    let mut set = dedup_urls.lock().unwrap();
+    if progress.cancel.load(Ordering::Relaxed) || limit_reached.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut extraction = extract_urls_from_csv(&source, options, &progress.cancel);
+    progress.files_done.fetch_add(1, Ordering::Relaxed);
+    let urls = std::mem::take(&mut extraction.urls);
+    let urls_found = urls.len();
+    total_found.fetch_add(urls_found, Ordering::Relaxed);
+
+    // Streaming hands the URLs straight to the writer instead of the shared map
+    let urls = match sink {
+        Some(sink) => {
+            if let Err(e) = sink.write_file_urls(&source.name, urls, options) {
+                extraction.error(e);
+            }
+            if sink.is_full() {
+                limit_reached.store(true, Ordering::Relaxed);
+            }
+            Vec::new()
+        }
+        None => urls,
+    };
+    log::info!(
+        "{}: {} URLs, {} invalid fields, {} outside the length bounds",
+        source.name,
+        urls_found,
+        extraction.invalid_urls.len(),
+        extraction.length_filtered
+    );
+    lock(reports).push(FileReport {
+        file: source.name.clone(),
+        urls_found,
+        invalid_urls: extraction.invalid_urls,
+        length_filtered: extraction.length_filtered,
+        missing_columns: extraction.missing_columns,
+        errors: extraction.errors,
+    });
+
+    // Deduplicate within the file first so the shared map is locked once per
+    // file, and the key computation (which parses each URL) runs unlocked
+    let mut local: HashMap<String, DedupEntry> = HashMap::new();
     for url in urls {
-        set.insert(url);
+        // The first URL seen for a key is kept
+        local
+            .entry(dedup_key(&dedup_form(&url, options.dedup_mode), options.dedup_regex.as_ref()))
+            .or_insert_with(|| DedupEntry {
+                known: master_list.is_some_and(|list| list.contains(&url)),
+                url,
+                sources: vec![source.name.clone()],
+                count: 0,
+            })
+            .count += 1;
+    }
+
+    let mut guard = lock(&dedup_urls);
+    let DedupUrls { entries, new } = &mut *guard;
+    for (key, file_entry) in local {
+        match entries.entry(key) {
+            Entry::Occupied(mut existing) => {
+                // Every file a URL appears in is recorded, in discovery order
+                let existing = existing.get_mut();
+                existing.count += file_entry.count;
+                if !existing.sources.contains(&source.name) {
+                    existing.sources.push(source.name.clone());
+                }
+            }
+            Entry::Vacant(slot) => {
+                // Once the limit is hit, new URLs are dropped but repeats of
+                // kept ones still count; master list hits never count toward it
+                if file_entry.known {
+                    slot.insert(file_entry);
+                } else if options.limit.is_some_and(|limit| *new >= limit) {
+                    limit_reached.store(true, Ordering::Relaxed);
+                } else {
+                    *new += 1;
+                    slot.insert(file_entry);
+                }
+            }
+        }
     }
 }
 
-fn process_directory(
-    directory_path: PathBuf,
-    workers: usize,
-    skip_header: bool,
-    exclude_file: Option<PathBuf>,
-    continue_on_error: bool,
-    header_name: String,
-) -> HashSet<String> {
-    let entries = fs::read_dir(&directory_path).unwrap_or_else(|e| {
-        panic!("Error reading directory {:?}: {}", directory_path, e);
-    });
-    let csv_files: Vec<PathBuf> = entries
-        .filter_map(|entry| {
-            let path = entry.ok()?.path();
-            if path
-                .extension()
-                .and_then(|s| s.to_str())
-                .map(|ext| ext.eq_ignore_ascii_case("csv"))
-                .unwrap_or(false)
-            {
-                Some(path)
-            } else {
-                None
+/// Why `process_directory` couldn't read any files.
+enum ProcessError {
+    /// The directory couldn't be listed or the worker pool didn't start
+    Failed(String),
+    /// Nothing in the directory is a CSV file the file patterns allow
+    NoCsvFiles(String),
+}
+
+impl fmt::Display for ProcessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProcessError::Failed(message) | ProcessError::NoCsvFiles(message) => f.write_str(message),
+        }
+    }
+}
+
+/// The sources in `directory_path` that `files` allows; finding none is an
+/// error, since an empty run would otherwise finish with "0 URLs" and no hint why.
+fn find_csv_sources(directory_path: &Path, recursive: bool, files: &FileFilter) -> Result<Vec<CsvSource>, ProcessError> {
+    let csv_files = collect_csv_sources(directory_path, recursive, files)
+        .map_err(|e| ProcessError::Failed(format!("Error reading directory {:?}: {}", directory_path, e)))?;
+    if csv_files.is_empty() {
+        let filtered = if files.is_empty() { "" } else { " matching the file patterns" };
+        let extensions: Vec<String> = files.extensions().iter().map(|ext| format!(".{}", ext)).collect();
+        return Err(ProcessError::NoCsvFiles(format!(
+            "No {} files{} found in {}",
+            extensions.join("/"),
+            filtered,
+            directory_path.display()
+        )));
+    }
+    Ok(csv_files)
+}
+
+/// One file's header row, from [`scan_headers`].
+struct FileHeaders {
+    file: String,
+    /// The header row, or why it couldn't be read
+    headers: Result<Vec<String>, String>,
+    /// Requested columns the header row lacks
+    missing: Vec<String>,
+}
+
+/// Reads the header row of every file a run over `directory_path` would
+/// read, without extracting anything, and notes which of the requested
+/// columns each one lacks. With `--column` indexes there are no names to
+/// check, so nothing is reported missing.
+fn scan_headers(directory_path: &Path, recursive: bool, options: &ExtractOptions) -> Result<Vec<FileHeaders>, ProcessError> {
+    let header_names: &[String] = if options.columns.is_empty() { &options.header_names } else { &[] };
+    let scans = find_csv_sources(directory_path, recursive, &options.files)?
+        .into_iter()
+        .map(|source| {
+            let delimiter = options.delimiter.for_path(Path::new(&source.name));
+            let headers = source
+                .open()
+                .map_err(|e| e.to_string())
+                .and_then(|file| {
+                    let mut rdr = build_csv_reader(file, options.trim, delimiter, options.encoding, true);
+                    rdr.headers().cloned().map_err(|e| e.to_string())
+                });
+            let missing = match &headers {
+                Ok(headers) => header_names
+                    .iter()
+                    .filter(|name| find_header(headers, name).is_none())
+                    .cloned()
+                    .collect(),
+                Err(_) => Vec::new(),
+            };
+            FileHeaders {
+                file: source.name,
+                headers: headers.map(|headers| headers.iter().map(str::to_string).collect()),
+                missing,
             }
         })
         .collect();
+    Ok(scans)
+}
 
-    let dedup_urls = Arc::new(Mutex::new(HashSet::new()));
+fn process_directory(
+    directory_path: PathBuf,
+    workers: usize,
+    recursive: bool,
+    options: &ExtractOptions,
+    progress: &Progress,
+    sink: Option<&StreamingWriter>,
+    master_list: Option<&MasterList>,
+) -> Result<ProcessingResult, ProcessError> {
+    let csv_files = find_csv_sources(&directory_path, recursive, &options.files)?;
+    progress.files_total.store(csv_files.len(), Ordering::Relaxed);
+    log::debug!("Found {} CSV files in {:?}", csv_files.len(), directory_path);
+
+    let dedup_urls = Arc::new(Mutex::new(DedupUrls::default()));
 
     let pool = ThreadPoolBuilder::new()
         .num_threads(workers)
         .build()
-        .unwrap();
-
-    let excluded_urls: HashSet<String> = exclude_file
-        .map(|path| {
-            fs::read_to_string(path)
-                .unwrap_or_else(|e| {
-                    eprintln!("Error reading exclude file: {}", e);
-                    String::new()
-                })
-                .lines()
-                .map(|line| line.trim().to_string())
-                .collect()
-        })
-        .unwrap_or_else(HashSet::new);
+        .map_err(|e| ProcessError::Failed(format!("Error starting {} worker threads: {}", workers, e)))?;
 
+    let total_found = AtomicUsize::new(0);
+    let reports = Mutex::new(Vec::new());
+    let limit_reached = AtomicBool::new(false);
     pool.scope(|s| {
         for file in csv_files {
             let dedup_urls = Arc::clone(&dedup_urls);
-            let header = header_name.clone();
+            let total_found = &total_found;
+            let reports = &reports;
+            let limit_reached = &limit_reached;
             s.spawn(move |_| {
-                process_file(file, dedup_urls, skip_header, continue_on_error, header);
+                process_file(file, dedup_urls, total_found, reports, options, progress, sink, master_list, limit_reached);
             });
         }
     });
+    let mut file_reports: Vec<FileReport> = reports.into_inner().unwrap_or_else(PoisonError::into_inner);
+    file_reports.sort_by(|a, b| a.file.cmp(&b.file));
+    let missing_columns: Vec<(String, String)> = file_reports
+        .iter()
+        .flat_map(|report| {
+            report
+                .missing_columns
+                .iter()
+                .map(|column| (report.file.clone(), column.clone()))
+        })
+        .collect();
 
-    let set = dedup_urls.lock().unwrap();
-    let mut filtered_urls = HashSet::new();
-    for url in set.iter() {
-        if !excluded_urls.contains(url) {
-            filtered_urls.insert(url.clone());
+    let map = lock(&dedup_urls);
+    let mut urls = HashSet::new();
+    let mut sources = HashMap::new();
+    let mut occurrences = HashMap::new();
+    let mut known_urls = Vec::new();
+    for entry in map.entries.values() {
+        if entry.known {
+            known_urls.push(entry.url.clone());
+            continue;
         }
+        urls.insert(entry.url.clone());
+        sources.insert(entry.url.clone(), entry.sources.clone());
+        occurrences.insert(entry.url.clone(), entry.count);
     }
-    filtered_urls
+    Ok(ProcessingResult {
+        urls,
+        sources,
+        occurrences,
+        total_found: total_found.into_inner(),
+        missing_columns,
+        file_reports,
+        limit_reached: limit_reached.into_inner(),
+        known_urls,
+    })
 }
 
-#[derive(PartialEq)]
-enum Tab {
-    Main,
-    Statistics,
-    Settings,
+/// Builds the run summary shown on the Statistics tab.
+fn run_statistics(
+    files_processed: usize,
+    result: &ProcessingResult,
+    excluded_count: usize,
+    start_time: Instant,
+    unique_count: usize,
+    live_check: Option<(usize, usize)>,
+) -> Statistics {
+    // total_found = every valid URL occurrence, before deduplication
+    // unique_count = URLs written after master list and exclusion filtering
+    // excluded_count = URLs that matched the exclusion list
+    // duplicates = everything else that was dropped as already seen, i.e.
+    // repeats within this batch plus master list hits (dead links aside).
+    // Saturating so inconsistent inputs can never underflow.
+    let dead_count = live_check.map(|(_, dead)| dead).unwrap_or(0);
+    let duplicate_urls = result
+        .total_found
+        .saturating_sub(unique_count + excluded_count + dead_count);
+
+    Statistics {
+        total_files_processed: files_processed,
+        total_urls_found: result.total_found,
+        distinct_urls: result.urls.len(),
+        unique_urls: unique_count,
+        excluded_urls: excluded_count,
+        invalid_urls: result.file_reports.iter().map(|report| report.invalid_urls.len()).sum(),
+        duplicate_urls,  // Use the correctly calculated value
+        file_errors: result.file_reports.iter().map(|report| report.errors.len()).sum(),
+        processing_time: start_time.elapsed().as_secs_f64(),
+        last_run: Some(Local::now().format("%Y-%m-%d %H:%M:%S").to_string()),
+        live_urls: live_check.map(|(live, _)| live),
+        dead_urls: live_check.map(|(_, dead)| dead),
+    }
 }
 
-struct ExportCsvLinksApp {
-    directory: String,
-    output: String,
-    skip_header: bool,
-    workers: usize,
-    exclude_file: String,
-    continue_on_error: bool,
-    master_list: MasterList,
-    master_list_path: String,
-    sample_file_path: String,
-    available_headers: Vec<String>, 
-    selected_header: String,
-    config: AppConfig,
-    status_message: String,
-    current_tab: Tab,
-    statistics: Statistics,
-    use_timestamp: bool,
-    enhanced_stats: EnhancedStatistics,
-}
-
-impl Default for ExportCsvLinksApp {
-    fn default() -> Self {
-        let config = AppConfig::load();
-        let mut master_list = MasterList::new();
-        
-        // Load master list if path exists
-        if !config.master_list_path.is_empty() && Path::new(&config.master_list_path).exists() {
-            if let Err(e) = master_list.load_from_file(&config.master_list_path) {
-                eprintln!("Error loading master list: {}", e);
-            }
-        }
-
-        let mut app = Self {
-            directory: config.directory.clone(),
-            output: config.output.clone(),
-            skip_header: config.skip_header,
-            workers: config.workers,
-            exclude_file: config.exclude_file.clone(),
-            continue_on_error: config.continue_on_error,
-            master_list,  // Use the loaded master list
-            master_list_path: config.master_list_path.clone(),
-            sample_file_path: config.sample_file_path.clone(),
-            available_headers: Vec::new(),
-            selected_header: config.selected_header.clone(),
-            config: config.clone(),
-            status_message: String::from("Ready"),
-            current_tab: Tab::Main,
-            statistics: config.statistics.clone(),
-            use_timestamp: config.use_timestamp,
-            enhanced_stats: EnhancedStatistics::new(),
-        };
-        
-        app.load_sample_csv();
-        app
-    }
+/// How the statistics charts are drawn.
+struct ChartOptions {
+    /// Domains shown in the domain chart
+    top_n: usize,
+    /// Seconds each chart may take to render before it is skipped
+    timeout_secs: u64,
+    /// Size of the domain chart in pixels; the trend chart is drawn at half
+    /// the width and two thirds the height
+    width: u32,
+    height: u32,
+    format: ChartFormat,
 }
 
-impl ExportCsvLinksApp {
-    fn load_sample_csv(&mut self) {
-        if let Ok(file) = File::open(&self.sample_file_path) {
-            let mut rdr = csv::Reader::from_reader(file);
-            if let Ok(headers) = rdr.headers() {
-                self.available_headers = headers
-                    .iter()
-                    .map(|h| h.to_string())
-                    .collect();
-                // If current selected header isn't in the list, select first available
-                if !self.available_headers.contains(&self.selected_header) {
-                    self.selected_header = self.available_headers
-                        .first()
-                        .map(|h| h.to_string())
-                        .unwrap_or_default();
-                }
-            }
-        }
+/// Writes the Markdown report for `stats` and `latest_run.json` (the
+/// `latest` run summary) to `stats_dir`, along with the charts when built
+/// with the `gui` feature; `urls` feed the URL length histogram.
+#[cfg_attr(not(feature = "gui"), allow(unused_variables))]
+fn write_statistics_files(
+    stats_dir: &Path,
+    stats: &EnhancedStatistics,
+    latest: &Statistics,
+    urls: &HashSet<String>,
+    charts: &ChartOptions,
+) {
+    if let Err(e) = fs::create_dir_all(stats_dir) {
+        log::error!("Error creating statistics directory {:?}: {}", stats_dir, e);
+        return;
     }
-    fn update(&mut self, ctx: &egui::Context, frame: &mut Frame) {
-        let accent_color = egui::Color32::from_rgb(28, 113, 216); // Define accent color once
-        
-        let mut style = (*ctx.style()).clone();
-        style.visuals.dark_mode = true;
-        style.visuals.override_text_color = Some(egui::Color32::WHITE);
-        style.visuals.extreme_bg_color = egui::Color32::from_rgb(30, 30, 30);
-        style.visuals.widgets.noninteractive.bg_fill = egui::Color32::from_rgb(50, 50, 50);
-        style.visuals.selection.bg_fill = accent_color; // Use accent color for selection
-        style.spacing.item_spacing = egui::vec2(10.0, 10.0);
-        style.spacing.window_margin = egui::Margin::same(10.0);
-        style.visuals.window_rounding = egui::Rounding::same(5.0);
-        ctx.set_style(style);
-
-        TopBottomPanel::top("tabs").show(ctx, |ui| {
-            ui.horizontal(|ui| {
-                if ui.selectable_label(self.current_tab == Tab::Main, "Main").clicked() {
-                    self.current_tab = Tab::Main;
-                }
-                if ui.selectable_label(self.current_tab == Tab::Statistics, "Statistics").clicked() {
-                    self.current_tab = Tab::Statistics;
-                }
-                if ui.selectable_label(self.current_tab == Tab::Settings, "Settings").clicked() {
-                    self.current_tab = Tab::Settings;
-                }
-            });
-        });
 
-        // Add spacing after tabs
-        CentralPanel::default().show(ctx, |ui| {
-            ui.add_space(10.0);
-            
-            egui::ScrollArea::vertical().show(ui, |ui| {
-                match self.current_tab {
-                    Tab::Main => self.render_main_tab(ui),
-                    Tab::Statistics => self.render_statistics_tab(ui),
-                    Tab::Settings => self.render_settings_tab(ui),
-                }
-            });
+    #[cfg(feature = "gui")]
+    charts::render_charts(stats, urls, stats_dir, charts);
 
-            // Status bar at the bottom
-            ui.with_layout(egui::Layout::bottom_up(egui::Align::LEFT), |ui| {
-                ui.add_space(4.0);
-                ui.separator();
-                ui.horizontal(|ui| {
-                    ui.label(&self.status_message);
-                });
-            });
-        });
+    let report_file = stats_dir.join("statistics_report.md");
+    if let Err(e) = stats.export_report(&report_file) {
+        log::error!("Failed to generate statistics report: {}", e);
+    }
 
-        // Check for any UI changes
-        if ctx.input(|i| i.pointer.any_pressed() || i.key_pressed(egui::Key::Enter)) {
-            self.save_config();
-        }
-    }
-
-    fn save_config(&mut self) {
-        self.config.directory = self.directory.clone();
-        self.config.output = self.output.clone();
-        self.config.skip_header = self.skip_header;
-        self.config.workers = self.workers;
-        self.config.exclude_file = self.exclude_file.clone();
-        self.config.continue_on_error = self.continue_on_error;
-        self.config.master_list_path = self.master_list_path.clone();
-        self.config.sample_file_path = self.sample_file_path.clone();
-        self.config.selected_header = self.selected_header.clone();
-        self.config.statistics = self.statistics.clone();
-        self.config.use_timestamp = self.use_timestamp;
-
-        if let Err(e) = self.config.save() {
-            eprintln!("Error saving config: {}", e);
-        }
-    }
-
-    fn update_statistics(&mut self, 
-        files_processed: usize,
-        all_urls: &HashSet<String>,
-        excluded_urls: &HashSet<String>,
-        start_time: std::time::Instant,
-        unique_count: usize
-    ) {
-        // Fix duplicate calculation:
-        // total_urls = all found URLs before any filtering
-        // unique_count = URLs after master list and exclusion filtering
-        // excluded_urls = URLs that matched exclusion list
-        // duplicates = URLs that were filtered by master list
-        let duplicate_urls = all_urls.len() - (unique_count + excluded_urls.len());
-        
-        self.statistics = Statistics {
-            total_files_processed: files_processed,
-            total_urls_found: all_urls.len(),
-            unique_urls: unique_count,
-            excluded_urls: excluded_urls.len(),
-            duplicate_urls,  // Use the correctly calculated value
-            processing_time: start_time.elapsed().as_secs_f64(),
-            last_run: Some(Local::now().format("%Y-%m-%d %H:%M:%S").to_string()),
-        };
-        
-        // Save statistics to config
-        self.config.statistics = self.statistics.clone();
-        self.save_config();
-        
-        // Update enhanced statistics
-        let session = ProcessingSession {
-            timestamp: Local::now(),
-            total_urls: all_urls.len(),
-            unique_urls: unique_count,
-            files_processed,
-            processing_time_secs: start_time.elapsed().as_secs_f64(),
-        };
-        
-        self.enhanced_stats.add_session(session);
-        self.enhanced_stats.update_domain_frequencies(&all_urls.iter().cloned().collect::<Vec<_>>());
-        
-        // Generate charts and report
-        let stats_dir = PathBuf::from("statistics");
-        if !stats_dir.exists() {
-            let _ = std::fs::create_dir(&stats_dir);
-        }
-        
-        let domain_chart = stats_dir.join("domain_distribution.png");
-        let trend_chart = stats_dir.join("historical_trends.png");
-        let report_file = stats_dir.join("statistics_report.md");
-        
-        if let Err(e) = self.enhanced_stats.generate_domain_distribution_chart(&domain_chart) {
-            eprintln!("Failed to generate domain distribution chart: {}", e);
-        }
-        if let Err(e) = self.enhanced_stats.generate_historical_trend_chart(&trend_chart) {
-            eprintln!("Failed to generate historical trend chart: {}", e);
-        }
-        if let Err(e) = self.enhanced_stats.export_report(&report_file) {
-            eprintln!("Failed to generate statistics report: {}", e);
-        }
-    }
-
-    fn render_main_tab(&mut self, ui: &mut egui::Ui) {
-        ui.heading("Export CSV Links");
-        egui::ScrollArea::vertical().show(ui, |ui| {
-            ui.label("Directory:");
-            if ui.add(TextEdit::singleline(&mut self.directory)).changed() {
-                self.save_config();
-            }
+    let latest_file = stats_dir.join("latest_run.json");
+    let written = serde_json::to_string_pretty(latest)
+        .map_err(std::io::Error::from)
+        .and_then(|json| fs::write(&latest_file, json));
+    if let Err(e) = written {
+        log::error!("Failed to write latest run summary: {}", e);
+    }
+}
 
-            ui.label("Output File:");
-            if ui.add(TextEdit::singleline(&mut self.output)).changed() {
-                self.save_config();
-            }
+/// How a finished extraction is filtered and written.
+struct OutputOptions {
+    format: OutputFormat,
+    eol: LineEnding,
+    sort: SortOrder,
+    append: bool,
+    with_source: bool,
+    include_first_seen: bool,
+    check_live: bool,
+    workers: usize,
+    timeout_secs: u64,
+    write_excluded: bool,
+    /// Write rejected fields to `invalid_urls.txt`
+    write_invalid: bool,
+    /// Add each URL's occurrence count and order by it, most frequent first
+    count_occurrences: bool,
+    /// Write `<output>.high_volume.txt` for domains above this many URLs
+    high_volume_threshold: Option<usize>,
+    /// Write `<output>.by_domain.txt` with the output URLs grouped by host
+    group_by_domain: bool,
+    /// Write at most this many URLs per host
+    max_per_domain: Option<usize>,
+    /// Run every filter but write nothing and leave the master list alone
+    dry_run: bool,
+    /// A previous output file or master list to compare this run's output
+    /// against, writing `new_urls.txt` and `removed_urls.txt`
+    diff_against: Option<PathBuf>,
+}
 
-            ui.label("Exclude File:");
-            if ui.add(TextEdit::singleline(&mut self.exclude_file)).changed() {
-                self.save_config();
-            }
+/// Counts from `write_output`.
+struct WriteSummary {
+    written: usize,
+    excluded: usize,
+    master_list_hits: usize,
+    /// URLs dropped by `max_per_domain`
+    domain_capped: usize,
+    live_check: Option<(usize, usize)>,
+    /// `(added, removed)` URLs compared with `diff_against`
+    diff: Option<(usize, usize)>,
+}
 
-            // Add column selector
-            if !self.available_headers.is_empty() {
-                ui.label("URL Column:");
-                let mut selected = self.selected_header.clone();
-                egui::ComboBox::from_id_source("header_selector")
-                    .selected_text(&selected)
-                    .show_ui(ui, |ui| {
-                        for header in &self.available_headers {
-                            if ui.selectable_value(
-                                &mut selected,
-                                header.clone(),
-                                header
-                            ).changed() {
-                                // Value will be updated after the loop
-                            }
-                        }
-                    });
-                if selected != self.selected_header {
-                    self.selected_header = selected;
-                    self.save_config();
-                }
-            }
+/// Drops excluded, already-known and dead URLs from `result`, writes the
+/// rest to `output_path` with any companion reports, and records them in
+/// the master list. Errors are returned ready for display.
+fn write_output(
+    result: &ProcessingResult,
+    exclusions: &Exclusions,
+    master_list: &mut MasterList,
+    output_path: &Path,
+    options: &OutputOptions,
+) -> Result<WriteSummary, String> {
+    let all_urls_set = &result.urls;
+
+    // When appending to a non-empty file, URLs it already holds are skipped
+    let appending = options.append && !is_stdout(output_path) && fs::metadata(output_path).is_ok_and(|m| m.len() > 0);
+    let existing_urls = if appending {
+        File::open(output_path)
+            .and_then(|file| read_output_urls(file, options.format))
+            .map_err(|e| format!("Error reading existing output file: {}", e))?
+    } else {
+        HashSet::new()
+    };
+    // Read before the output is opened, which may be the same file
+    let previous_urls = match &options.diff_against {
+        Some(path) => Some(read_url_file(path).map_err(|e| format!("Error reading {:?} to compare against: {}", path, e))?),
+        None => None,
+    };
 
-            // Style the Process button with better contrast
-            let process_button = egui::Button::new("Process")
-                .fill(egui::Color32::from_rgb(28, 113, 216))  // Same accent color as tabs
-                .stroke(egui::Stroke::NONE);
-                
-            if ui.add(process_button).clicked() {
-                self.status_message = "Processing...".to_string();
-                let start_time = std::time::Instant::now();
-                
-                let directory_path = PathBuf::from(self.directory.clone());
-                
-                // Fix the ownership issue in files_processed counting
-                let files_processed = fs::read_dir(&directory_path)
-                    .map(|entries| entries
-                        .filter(|entry| {
-                            entry.as_ref()
-                                .ok()
-                                .map(|e| {
-                                    e.path()
-                                        .extension()
-                                        .and_then(|ext| ext.to_str())
-                                        .map(|ext| ext.eq_ignore_ascii_case("csv"))
-                                        .unwrap_or(false)
-                                })
-                                .unwrap_or(false)
-                        })
-                        .count())
-                    .unwrap_or(0);
-
-                let mut output_path = PathBuf::from(self.output.clone());
-                
-                // Add timestamp to filename if enabled
-                if self.use_timestamp {
-                    if let Some(ext) = output_path.extension().and_then(|e| e.to_str()) {
-                        if let Some(stem) = output_path.file_stem().and_then(|s| s.to_str()) {
-                            let timestamp = Local::now().format("_%Y%m%d_%H%M%S");
-                            output_path.set_file_name(format!("{}{}.{}", stem, timestamp, ext));
-                        }
-                    }
-                }
+    // Preview runs the whole pipeline but writes nothing
+    let writer = if options.dry_run {
+        None
+    } else {
+        Some(open_output(output_path, options.append).map_err(|e| format!("Error creating output file: {}", e))?)
+    };
 
-                let exclude_file_path = if !self.exclude_file.is_empty() {
-                    Some(PathBuf::from(self.exclude_file.clone()))
-                } else {
-                    None
-                };
-
-                let excluded_urls: HashSet<String> = exclude_file_path
-                    .as_ref()
-                    .map(|path| {
-                        fs::read_to_string(path)
-                            .unwrap_or_else(|e| {
-                                eprintln!("Error reading exclude file: {}", e);
-                                String::new()
-                            })
-                            .lines()
-                            .map(|line| line.trim().to_string())
-                            .collect()
-                    })
-                    .unwrap_or_else(HashSet::new);
-
-                // Get the URLs from processing and store in a variable we won't move
-                let all_urls_set = process_directory(
-                    directory_path.clone(),
-                    self.workers,
-                    self.skip_header,
-                    exclude_file_path,
-                    self.continue_on_error,
-                    self.selected_header.clone(),
-                );
-
-                // Write results to both output file and master list
-                if let Ok(file) = File::create(&output_path) {
-                    let mut writer = BufWriter::new(file);
-                    let mut count = 0;
-                    for url in &all_urls_set {  // Use reference to avoid moving
-                        if !excluded_urls.contains(url) && !self.master_list.contains(url) {
-                            if let Err(e) = writeln!(writer, "{}", url) {
-                                self.status_message = format!("Error writing to file: {}", e);
-                                break;
-                            }
-                            self.master_list.add(url.clone());
-                            count += 1;
-                        }
-                    }
+    let mut accepted: Vec<&String> = Vec::new();
+    let mut dropped: Vec<(&String, &str)> = Vec::new();
+    let run_started = Local::now();
+
+    // The one place exclusions, the master list and the existing output are
+    // applied; live checking only adds dead links on top
+    let drop_reason = |url: &String| {
+        if exclusions.is_excluded(url) {
+            Some("exclude list")
+        } else if master_list.contains(url) {
+            Some("master list")
+        } else if existing_urls.contains(url) {
+            Some("already in output")
+        } else {
+            None
+        }
+    };
+    // Skipped during extraction, but still reported with why they were dropped
+    for url in &result.known_urls {
+        dropped.push((url, drop_reason(url).unwrap_or("master list")));
+    }
 
-                    // Save updated master list
-                    if self.master_list.is_loaded() {
-                        if let Err(e) = self.master_list.save() {
-                            self.status_message = format!("Error saving master list: {}", e);
-                        }
+    // Only URLs that would otherwise be written are checked
+    let mut dead_urls: HashSet<&String> = HashSet::new();
+    let mut live_check = None;
+    if options.check_live {
+        let candidates: Vec<&String> = all_urls_set.iter().filter(|url| drop_reason(url).is_none()).collect();
+        match partition_live(&candidates, options.workers, Duration::from_secs(options.timeout_secs)) {
+            Ok((live, dead)) => {
+                if !options.dry_run {
+                    let dead_path = output_path.with_file_name("dead_urls.txt");
+                    let report: String = dead.iter().map(|url| format!("{}\n", url)).collect();
+                    if let Err(e) = fs::write(&dead_path, report) {
+                        log::error!("Error writing dead URLs: {}", e);
                     }
-
-                    self.update_statistics(
-                        files_processed,
-                        &all_urls_set,  // Pass reference
-                        &excluded_urls,
-                        start_time,
-                        count
-                    );
-
-                    self.status_message = format!("Processed {} unique URLs", count);
-                } else {
-                    self.status_message = "Error creating output file".to_string();
                 }
+                live_check = Some((live.len(), dead.len()));
+                dead_urls.extend(dead);
             }
-        });
+            Err(e) => log::error!("Live check failed: {}", e),
+        }
     }
 
-    fn render_statistics_tab(&mut self, ui: &mut egui::Ui) {
-        ui.horizontal(|ui| {
-            ui.heading("Statistics Dashboard");
-            if ui.button("🔄").on_hover_text("Reset Statistics").clicked() {
-                self.statistics = Statistics {
-                    total_files_processed: 0,
-                    total_urls_found: 0,
-                    unique_urls: 0,
-                    excluded_urls: 0,
-                    duplicate_urls: 0,
-                    processing_time: 0.0,
-                    last_run: None,
-                };
-                self.config.statistics = self.statistics.clone();
-                self.save_config();
-            }
-            // Try a more general and visible cleaning symbol
-            if ui.button("⚡").on_hover_text("Clean Master List").clicked() {
-                if self.master_list.is_loaded() {
-                    let _cleaned = self.master_list.deduplicate(); // Using _ to indicate intentionally unused
-                    if let Err(e) = self.master_list.save() {
-                        self.status_message = format!("Error saving master list after cleaning: {}", e);
-                    } else {
-                        self.status_message = "Master list cleaned".to_string();
-                    }
-                } else {
-                    self.status_message = "No master list loaded".to_string();
-                }
+    for url in all_urls_set {
+        let reason = drop_reason(url).or_else(|| dead_urls.contains(url).then_some("dead link"));
+        if let Some(reason) = reason {
+            log::debug!("Dropped {} ({})", url, reason);
+            dropped.push((url, reason));
+            continue;
+        }
+        accepted.push(url);
+    }
+    sort_urls(&mut accepted, options.sort);
+    if options.count_occurrences {
+        // Stable, so URLs with equal counts keep the chosen sort order
+        accepted.sort_by_key(|url| std::cmp::Reverse(result.occurrences.get(*url).copied().unwrap_or(0)));
+    }
+    // Capped last, so each host keeps its first URLs in output order
+    let mut domain_capped = 0;
+    if let Some(cap) = options.max_per_domain {
+        let mut per_host: HashMap<String, usize> = HashMap::new();
+        accepted.retain(|url| {
+            let count = per_host.entry(clean_host(url).unwrap_or_default()).or_insert(0);
+            *count += 1;
+            if *count <= cap {
+                return true;
             }
+            log::debug!("Dropped {} (domain cap)", url);
+            dropped.push((*url, "domain cap"));
+            domain_capped += 1;
+            false
         });
-        
-        ui.add_space(10.0);
-        egui::Grid::new("stats_grid")
-            .num_columns(2)
-            .spacing([40.0, 4.0])
-            .show(ui, |ui| {
-                ui.label("Total Files Processed:");
-                ui.label(format!("{}", self.statistics.total_files_processed));
-                ui.end_row();
-
-                ui.label("Total URLs Found:");
-                ui.label(format!("{}", self.statistics.total_urls_found));
-                ui.end_row();
-
-                ui.label("Unique URLs:");
-                ui.label(format!("{}", self.statistics.unique_urls));
-                ui.end_row();
-
-                ui.label("Excluded URLs:");
-                ui.label(format!("{}", self.statistics.excluded_urls));
-                ui.end_row();
-
-                ui.label("Duplicate URLs:");
-                ui.label(format!("{}", self.statistics.duplicate_urls));
-                ui.end_row();
-
-                ui.label("Processing Time:");
-                ui.label(format!("{:.2}s", self.statistics.processing_time));
-                ui.end_row();
-
-                if let Some(last_run) = &self.statistics.last_run {
-                    ui.label("Last Run:");
-                    ui.label(last_run);
-                    ui.end_row();
-                }
-            });
-        
-        ui.add_space(20.0);
-        ui.heading("Enhanced Statistics");
-        
-        if ui.button("Open Statistics Directory").clicked() {
-            if let Err(e) = std::process::Command::new("explorer")
-                .arg("statistics")
-                .spawn() {
-                eprintln!("Failed to open statistics directory: {}", e);
+    }
+
+    let diff = previous_urls.map(|previous| {
+        let current: HashSet<&str> = accepted.iter().map(|url| url.as_str()).collect();
+        let added: Vec<&str> = accepted
+            .iter()
+            .map(|url| url.as_str())
+            .filter(|url| !previous.contains(*url))
+            .collect();
+        let mut removed: Vec<&str> = previous
+            .iter()
+            .map(String::as_str)
+            .filter(|url| !current.contains(url))
+            .collect();
+        removed.sort_unstable();
+        if !options.dry_run {
+            let written = write_url_list(&output_path.with_file_name("new_urls.txt"), added.iter().copied())
+                .and_then(|_| write_url_list(&output_path.with_file_name("removed_urls.txt"), removed.iter().copied()));
+            if let Err(e) = written {
+                log::error!("Error writing diff report: {}", e);
             }
         }
-        
-        ui.add_space(10.0);
-        ui.label("Enhanced statistics are available in the 'statistics' directory:");
-        ui.label("- Domain distribution chart (domain_distribution.png)");
-        ui.label("- Historical trends chart (historical_trends.png)");
-        ui.label("- Detailed statistics report (statistics_report.md)");
-    }
+        (added.len(), removed.len())
+    });
 
-    fn render_settings_tab(&mut self, ui: &mut egui::Ui) {
-        ui.heading("Settings");
-        ui.add_space(10.0);
+    let summary = WriteSummary {
+        written: accepted.len(),
+        excluded: dropped.iter().filter(|(_, reason)| *reason == "exclude list").count(),
+        master_list_hits: dropped.iter().filter(|(_, reason)| *reason == "master list").count(),
+        domain_capped,
+        live_check,
+        diff,
+    };
+    let writer = match writer {
+        Some(writer) => writer,
+        None => return Ok(summary),
+    };
 
-        // Add timestamp checkbox near the top
-        if ui.checkbox(&mut self.use_timestamp, "Add timestamp to output filename").changed() {
-            self.save_config();
+    let first_seen = options.include_first_seen.then(|| run_started.to_rfc3339());
+    let records: Vec<OutputRecord> = accepted
+        .iter()
+        .map(|url| {
+            let sources = result.sources.get(*url).map(Vec::as_slice).unwrap_or_default();
+            OutputRecord {
+                url: url.as_str(),
+                source_file: sources.first().map(String::as_str).unwrap_or_default(),
+                domain: clean_host(url),
+                first_seen: first_seen.clone(),
+                count: options.count_occurrences.then(|| result.occurrences.get(*url).copied().unwrap_or(0)),
+                all_sources: options.with_source.then_some(sources),
+            }
+        })
+        .collect();
+    write_records(writer, options.format, &records, !appending, options.eol)
+        .map_err(|e| format!("Error writing to file: {}", e))?;
+    for url in &accepted {
+        let source = result.sources.get(*url).and_then(|s| s.first()).map(String::as_str);
+        master_list.add((*url).clone(), run_started, source);
+    }
+
+    if options.write_excluded {
+        let excluded_path = companion_path(output_path, "excluded.txt");
+        if let Err(e) = write_excluded_report(&excluded_path, &dropped) {
+            log::error!("Error writing excluded URLs: {}", e);
         }
-        if self.use_timestamp {
-            ui.small("Example: output_20240216_235959.txt");
+    }
+
+    if options.write_invalid {
+        if let Err(e) = write_invalid_report(&output_path.with_file_name("invalid_urls.txt"), &result.file_reports) {
+            log::error!("Error writing invalid URLs: {}", e);
         }
+    }
 
-        ui.add_space(10.0);
+    if options.group_by_domain {
+        if let Err(e) = write_domain_groups(&companion_path(output_path, "by_domain.txt"), &accepted) {
+            log::error!("Error writing domain breakdown: {}", e);
+        }
+    }
 
-        // Move worker count setting here
-        ui.label("Workers:");
-        if ui.add(egui::Slider::new(&mut self.workers, 1..=16).integer()).changed() {
-            self.save_config();
+    if let Some(threshold) = options.high_volume_threshold {
+        let domains = high_volume_domains(all_urls_set, threshold);
+        let report_path = companion_path(output_path, "high_volume.txt");
+        if let Err(e) = write_high_volume_report(&report_path, &domains) {
+            log::error!("Error writing high-volume domain report: {}", e);
         }
+    }
+
+    // Save updated master list
+    if master_list.is_loaded() {
+        master_list
+            .save()
+            .map_err(|e| format!("Error saving master list: {}", e))?;
+    }
 
-        if ui.checkbox(&mut self.skip_header, "Skip Header").changed() {
-            self.save_config();
+    Ok(summary)
+}
+
+/// Log level for `--quiet` / `--verbose`. Warnings (unreadable files,
+/// missing columns) show by default; `RUST_LOG` overrides the flags.
+fn init_logging(quiet: bool, verbose: u8) {
+    let level = match (quiet, verbose) {
+        (true, _) => log::LevelFilter::Error,
+        (false, 0) => log::LevelFilter::Warn,
+        (false, 1) => log::LevelFilter::Info,
+        (false, 2) => log::LevelFilter::Debug,
+        (false, _) => log::LevelFilter::Trace,
+    };
+    env_logger::Builder::new()
+        .filter_level(level)
+        .parse_default_env()
+        .format_timestamp(None)
+        .format_target(false)
+        .init();
+}
+
+/// Command-line names of every value of a `ValueEnum` setting.
+fn value_names<T: clap::ValueEnum>() -> Vec<String> {
+    T::value_variants()
+        .iter()
+        .filter_map(|value| value.to_possible_value())
+        .map(|value| value.get_name().to_string())
+        .collect()
+}
+
+/// The JSON printed by `--capabilities`: compiled-in features plus the
+/// defaults as this run would resolve them.
+fn capabilities(args: &Args) -> serde_json::Value {
+    let config = AppConfig::load();
+    let statistics_dir = args
+        .statistics_dir
+        .clone()
+        .unwrap_or_else(|| AppConfig::resolve_statistics_dir(&config.statistics_dir));
+    let features: Vec<&str> = [("gui", cfg!(feature = "gui"))]
+        .into_iter()
+        .filter_map(|(name, enabled)| enabled.then_some(name))
+        .collect();
+    serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "features": features,
+        "capabilities": {
+            "gui": cfg!(feature = "gui"),
+            "charts": cfg!(feature = "gui"),
+            "gzip_input": true,
+            "compressed_input": COMPRESSED_EXTENSIONS,
+            "zip_input": true,
+            "live_check": true,
+            "streaming": true,
+            "output_formats": value_names::<OutputFormat>(),
+            "chart_formats": value_names::<ChartFormat>(),
+        },
+        "workers": resolve_workers(args.workers),
+        "available_cores": available_workers(),
+        "config_path": AppConfig::config_path(),
+        "history_path": AppConfig::data_path(HISTORY_FILE),
+        "statistics_dir": statistics_dir,
+        "config": config,
+    })
+}
+
+fn main() {
+    let mut args = Args::parse();
+    init_logging(args.quiet, args.verbose);
+    if let Some(path) = args.config.take() {
+        AppConfig::set_config_path(path);
+    }
+    if let Some(name) = args.profile.take() {
+        if let Err(e) = AppConfig::set_profile(name) {
+            eprintln!("Error: {}", e);
+            std::process::exit(cli::EXIT_ERROR);
         }
-        if ui.checkbox(&mut self.continue_on_error, "Continue on Error").changed() {
-            self.save_config();
+    }
+    if args.capabilities {
+        match serde_json::to_string_pretty(&capabilities(&args)) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(cli::EXIT_ERROR);
+            }
         }
+        return;
+    }
+    let result = match args.directory.take() {
+        Some(directory) => cli::run(directory, args),
+        #[cfg(feature = "gui")]
+        None => gui::run().map_err(|e| CliError::from(e.to_string())),
+        #[cfg(not(feature = "gui"))]
+        None => Err(CliError::from("no input directory given (this build has no GUI)".to_string())),
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {}", e.message);
+        std::process::exit(e.code);
+    }
+}
 
-        ui.add_space(10.0);
-        ui.label("Master List File:");
-        if ui.text_edit_singleline(&mut self.master_list_path).changed() {
-            if Path::new(&self.master_list_path).exists() {
-                if let Err(e) = self.master_list.load_from_file(&self.master_list_path) {
-                    eprintln!("Error loading master list: {}", e);
-                }
-            }
-            self.save_config();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result_with(total_found: usize, urls: &[&str]) -> ProcessingResult {
+        ProcessingResult {
+            urls: urls.iter().map(|url| url.to_string()).collect(),
+            sources: HashMap::new(),
+            occurrences: HashMap::new(),
+            total_found,
+            missing_columns: Vec::new(),
+            file_reports: Vec::new(),
+            limit_reached: false,
+            known_urls: Vec::new(),
         }
+    }
 
-        if self.master_list.is_loaded() {
-            ui.label("Master list is loaded and will filter processed URLs");
+    /// Extraction settings as the command line defaults them, reading the
+    /// `Apply Url` column.
+    fn test_options() -> ExtractOptions {
+        ExtractOptions {
+            skip_header: false,
+            no_header: false,
+            continue_on_error: true,
+            read_retries: 0,
+            read_retry_delay_ms: 0,
+            header_names: vec![String::from("Apply Url")],
+            columns: Vec::new(),
+            trim: CsvTrim::None,
+            delimiter: Delimiter::Auto,
+            encoding: InputEncoding::Auto,
+            dedup_regex: None,
+            dedup_mode: DedupMode::default(),
+            normalize: false,
+            tracking_params: Vec::new(),
+            strip_query: false,
+            strip_fragment: false,
+            domain_filter: DomainFilter::new(Vec::<String>::new(), Vec::<String>::new()),
+            rewrites: Vec::new(),
+            schemes: vec![String::from("http"), String::from("https")],
+            fallback_column: None,
+            split_cells: false,
+            scan_text: false,
+            parallel_records: false,
+            max_records: None,
+            limit: None,
+            min_url_len: None,
+            max_url_len: None,
+            files: FileFilter::default(),
         }
+    }
+
+    /// A fresh directory under the system temp dir, unique to this test.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("csv_link_extractor_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
 
-        ui.add_space(10.0);
-        ui.label("Sample CSV:");
-        if ui.text_edit_singleline(&mut self.sample_file_path).changed() {
-            if Path::new(&self.sample_file_path).exists() {
-                self.load_sample_csv();
+    /// Times a run over 1000 small files, where every worker merging URLs
+    /// into the shared map contends for its lock. Run with
+    /// `cargo test --release -- --ignored --nocapture bench_many_small_files`.
+    #[test]
+    #[ignore]
+    fn bench_many_small_files() {
+        const FILES: usize = 1000;
+        const ROWS: usize = 200;
+        let dir = temp_dir("bench_many_small_files");
+        for file in 0..FILES {
+            let mut csv = String::from("Company,Apply Url\n");
+            for row in 0..ROWS {
+                // Half the URLs repeat across files so merging also deduplicates
+                let id = if row % 2 == 0 { row } else { file * ROWS + row };
+                csv.push_str(&format!("Company {},https://jobs{}.example.com/apply/{}\n", row, id % 97, id));
             }
-            self.save_config();
+            fs::write(dir.join(format!("export_{:04}.csv", file)), csv).unwrap();
         }
-    }
 
-    fn save(&mut self, _storage: &mut dyn Storage) {
-        self.save_config();
+        let options = test_options();
+        for workers in [1, available_workers()] {
+            let started = Instant::now();
+            let result = process_directory(dir.clone(), workers, false, &options, &Progress::default(), None, None)
+                .unwrap_or_else(|e| panic!("{}", e));
+            println!(
+                "{} files, {} workers: {} unique of {} URLs in {:.1?}",
+                FILES,
+                workers,
+                result.urls.len(),
+                result.total_found,
+                started.elapsed()
+            );
+            assert_eq!(result.file_reports.len(), FILES);
+        }
+        fs::remove_dir_all(&dir).unwrap();
     }
-}
 
-impl App for ExportCsvLinksApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
-        self.update(ctx, _frame);
+    #[test]
+    fn master_list_hits_are_kept_out_of_the_limit() {
+        let dir = temp_dir("master_list_limit");
+        let csv = "Company,Apply Url\nA,https://a.example.com/\nB,https://b.example.com/\nC,https://c.example.com/\n";
+        fs::write(dir.join("export.csv"), csv).unwrap();
+        let mut master_list = MasterList::new();
+        master_list.add(String::from("https://a.example.com/"), Local::now(), None);
+
+        let options = ExtractOptions { limit: Some(2), ..test_options() };
+        let result =
+            process_directory(dir.clone(), 1, false, &options, &Progress::default(), None, Some(&master_list))
+                .unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(result.known_urls, ["https://a.example.com/"]);
+        assert_eq!(result.urls.len(), 2);
+        assert!(!result.limit_reached);
+        fs::remove_dir_all(&dir).unwrap();
     }
 
-    fn save(&mut self, _storage: &mut dyn Storage) { // Added underscore to unused parameter
-        self.save_config();
+    #[test]
+    fn run_statistics_counts_duplicates() {
+        let result = result_with(10, &["https://a.com", "https://b.com", "https://c.com"]);
+        let stats = run_statistics(2, &result, 1, Instant::now(), 3, Some((3, 2)));
+        assert_eq!(stats.total_urls_found, 10);
+        assert_eq!(stats.distinct_urls, 3);
+        assert_eq!(stats.unique_urls, 3);
+        assert_eq!(stats.excluded_urls, 1);
+        assert_eq!(stats.duplicate_urls, 4);
+        assert_eq!((stats.live_urls, stats.dead_urls), (Some(3), Some(2)));
     }
-}
 
-fn main() -> Result<(), eframe::Error> {
-    let options = NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_inner_size(egui::vec2(400.0, 660.0))
-            .with_resizable(false), // Disable window resizing
-        persist_window: true,
-        ..Default::default()
-    };
-    
-    eframe::run_native(
-        "Export CSV Links",
-        options,
-        Box::new(|_cc| Box::new(ExportCsvLinksApp::default())),
-    )
+    #[test]
+    fn run_statistics_does_not_underflow() {
+        // The master list already holds four of the six URLs, so it removes
+        // more than the distinct URLs left over after writing
+        let dir = temp_dir("run_statistics_master_list");
+        let csv = "Company,Apply Url\nA,https://a.example.com/\nA,https://a.example.com/\nB,https://b.example.com/\n\
+                   C,https://c.example.com/\nD,https://d.example.com/\nE,https://e.example.com/\nF,https://f.example.com/\n\
+                   F,https://f.example.com/\n";
+        fs::write(dir.join("export.csv"), csv).unwrap();
+        let mut master_list = MasterList::new();
+        for url in [
+            "https://a.example.com/",
+            "https://b.example.com/",
+            "https://c.example.com/",
+            "https://d.example.com/",
+        ] {
+            master_list.add(String::from(url), Local::now(), None);
+        }
+
+        let result = process_directory(
+            dir.clone(),
+            1,
+            false,
+            &test_options(),
+            &Progress::default(),
+            None,
+            Some(&master_list),
+        )
+        .unwrap_or_else(|e| panic!("{}", e));
+        let output_options = OutputOptions {
+            format: OutputFormat::Txt,
+            eol: LineEnding::Lf,
+            sort: SortOrder::Alpha,
+            append: false,
+            with_source: false,
+            include_first_seen: false,
+            check_live: false,
+            workers: 1,
+            timeout_secs: 0,
+            write_excluded: false,
+            write_invalid: false,
+            count_occurrences: false,
+            high_volume_threshold: None,
+            group_by_domain: false,
+            max_per_domain: None,
+            dry_run: true,
+            diff_against: None,
+        };
+        let summary = write_output(
+            &result,
+            &Exclusions::default(),
+            &mut master_list,
+            &dir.join("links.txt"),
+            &output_options,
+        )
+        .unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(summary.master_list_hits, 4);
+
+        let stats = run_statistics(1, &result, summary.excluded, Instant::now(), summary.written, summary.live_check);
+        assert_eq!(stats.total_urls_found, 8);
+        assert_eq!(stats.distinct_urls, 2);
+        assert_eq!(stats.unique_urls, 2);
+        assert_eq!(stats.excluded_urls, 0);
+        // Four master list hits plus the repeats of a and f
+        assert_eq!(stats.duplicate_urls, 6);
+
+        let stats = run_statistics(0, &result_with(0, &[]), 1, Instant::now(), 0, None);
+        assert_eq!(stats.duplicate_urls, 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
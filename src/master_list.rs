@@ -1,49 +1,241 @@
-use std::collections::HashSet;
-use std::fs::File;
-use std::io::{self, BufRead, Write};
-use std::path::Path;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use crate::app_config::DedupMode;
+use crate::url_utils::{canonical_form, dedup_form};
 
+/// What is known about a master list entry.
+#[derive(Clone, Default)]
+struct Entry {
+    first_seen: Option<DateTime<Local>>,
+    source: Option<String>,
+}
+
+/// One entry of a JSON master list.
+#[derive(Serialize, Deserialize)]
+struct Record {
+    url: String,
+    #[serde(default)]
+    first_seen: Option<DateTime<Local>>,
+    #[serde(default)]
+    source: Option<String>,
+}
+
+/// URLs seen in previous runs, each with the time it was first added.
+///
+/// The on-disk format follows the file extension. A `.json` list is an array
+/// of `{url, first_seen, source}` records. Anything else is plain text: one
+/// URL per line, optionally followed by a tab and an RFC 3339 `first_seen`
+/// timestamp. Lines without a timestamp (lists written by older versions)
+/// load with an unknown `first_seen`; the text format doesn't keep sources.
+#[derive(Clone)]
 pub struct MasterList {
-    urls: HashSet<String>,
+    urls: HashMap<String, Entry>,
     file_path: Option<String>,
+    max_backups: usize,
+    /// How `contains` compares URLs
+    dedup_mode: DedupMode,
+    /// `dedup_form` of every entry, kept in step with `urls`
+    keys: HashSet<String>,
 }
 
 impl MasterList {
     pub fn new() -> Self {
         Self {
-            urls: HashSet::new(),
+            urls: HashMap::new(),
             file_path: None,
+            max_backups: 5,
+            dedup_mode: DedupMode::default(),
+            keys: HashSet::new(),
         }
     }
 
+    pub fn set_dedup_mode(&mut self, mode: DedupMode) {
+        self.dedup_mode = mode;
+        self.rebuild_keys();
+    }
+
+    fn rebuild_keys(&mut self) {
+        self.keys = self
+            .urls
+            .keys()
+            .map(|url| dedup_form(url, self.dedup_mode))
+            .collect();
+    }
+
+    /// How many `.bak` copies `save` keeps; 0 disables backups.
+    pub fn set_max_backups(&mut self, max_backups: usize) {
+        self.max_backups = max_backups;
+    }
+
     pub fn load_from_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
-        let file = File::open(&path)?;
-        let reader = io::BufReader::new(file);
-        for line in reader.lines() {
-            if let Ok(url) = line {
-                self.urls.insert(url.trim().to_string());
-            }
+        for (url, entry) in read_entries(path.as_ref())? {
+            self.insert(&url, entry);
         }
         self.file_path = Some(path.as_ref().to_string_lossy().into_owned());
         Ok(())
     }
 
+    /// Adds every entry of the list at `path` (in either format) without
+    /// making it this list's file. URLs listed in both keep the earlier
+    /// `first_seen` and any known source. Returns how many URLs were new.
+    pub fn merge_from_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<usize> {
+        let mut added = 0;
+        for (url, entry) in read_entries(path.as_ref())? {
+            match self.urls.get_mut(&url) {
+                Some(existing) => {
+                    existing.first_seen = match (existing.first_seen, entry.first_seen) {
+                        (Some(a), Some(b)) => Some(a.min(b)),
+                        (a, b) => a.or(b),
+                    };
+                    if existing.source.is_none() {
+                        existing.source = entry.source;
+                    }
+                }
+                // A different spelling of a listed URL isn't new
+                None if self.contains(&url) => {}
+                None => {
+                    self.insert(&url, entry);
+                    added += 1;
+                }
+            }
+        }
+        Ok(added)
+    }
+
+    fn insert(&mut self, url: &str, entry: Entry) {
+        if !url.is_empty() {
+            self.keys.insert(dedup_form(url, self.dedup_mode));
+            self.urls.insert(url.to_string(), entry);
+        }
+    }
+
+    /// Writes the list back to its file, first copying the existing file to a
+    /// timestamped `<file>.<time>.bak` next to it.
     pub fn save(&self) -> io::Result<()> {
         if let Some(path) = &self.file_path {
-            let mut file = File::create(path)?;
-            for url in &self.urls {
-                writeln!(file, "{}", url)?;
+            self.backup(Path::new(path))?;
+            let mut file = BufWriter::new(File::create(path)?);
+            let mut urls: Vec<(&String, &Entry)> = self.urls.iter().collect();
+            urls.sort_by(|a, b| a.0.cmp(b.0));
+            if is_json(Path::new(path)) {
+                let records: Vec<Record> = urls
+                    .into_iter()
+                    .map(|(url, entry)| Record {
+                        url: url.clone(),
+                        first_seen: entry.first_seen,
+                        source: entry.source.clone(),
+                    })
+                    .collect();
+                serde_json::to_writer_pretty(&mut file, &records)?;
+                writeln!(file)?;
+            } else {
+                for (url, entry) in urls {
+                    match entry.first_seen {
+                        Some(timestamp) => writeln!(file, "{}\t{}", url, timestamp.to_rfc3339())?,
+                        None => writeln!(file, "{}", url)?,
+                    }
+                }
             }
+            file.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the list file with its most recent backup and reloads it.
+    /// Returns the backup used, or `None` if there is none.
+    pub fn restore_backup(&mut self) -> io::Result<Option<PathBuf>> {
+        let path = match &self.file_path {
+            Some(path) => PathBuf::from(path),
+            None => return Ok(None),
+        };
+        let latest = match backups_of(&path)?.pop() {
+            Some(latest) => latest,
+            None => return Ok(None),
+        };
+        fs::rename(&latest, &path)?;
+        self.urls.clear();
+        self.keys.clear();
+        self.load_from_file(&path)?;
+        Ok(Some(latest))
+    }
+
+    fn backup(&self, path: &Path) -> io::Result<()> {
+        if self.max_backups == 0 || !path.exists() {
+            return Ok(());
+        }
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let stamp = Local::now().format("%Y%m%d_%H%M%S_%3f");
+        fs::copy(path, path.with_file_name(format!("{}.{}.bak", file_name, stamp)))?;
+
+        let backups = backups_of(path)?;
+        let excess = backups.len().saturating_sub(self.max_backups);
+        for old in &backups[..excess] {
+            fs::remove_file(old)?;
         }
         Ok(())
     }
 
+    /// Whether an entry matches `url` under the current dedup mode.
     pub fn contains(&self, url: &str) -> bool {
-        self.urls.contains(url)
+        self.urls.contains_key(url) || self.keys.contains(&dedup_form(url, self.dedup_mode))
+    }
+
+    /// Adds `url`, found in `source`, keeping the existing entry if it is
+    /// already listed.
+    pub fn add(&mut self, url: String, first_seen: DateTime<Local>, source: Option<&str>) {
+        self.keys.insert(dedup_form(&url, self.dedup_mode));
+        self.urls.entry(url).or_insert_with(|| Entry {
+            first_seen: Some(first_seen),
+            source: source.map(str::to_string),
+        });
+    }
+
+    /// When `url` was first added, if it is listed and the time is known.
+    pub fn first_seen(&self, url: &str) -> Option<DateTime<Local>> {
+        self.urls.get(url).and_then(|entry| entry.first_seen)
+    }
+
+    /// Returns `(url, master_entry)` pairs where a URL is not in the list
+    /// verbatim but matches an entry once both are canonicalized, i.e. the
+    /// filter and the list disagree on the URL's form.
+    pub fn find_normalization_mismatches<'a, I>(&self, urls: I) -> Vec<(String, String)>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let canonical: HashMap<String, &String> = self
+            .urls
+            .keys()
+            .map(|entry| (canonical_form(entry), entry))
+            .collect();
+
+        urls.into_iter()
+            .filter(|url| !self.urls.contains_key(*url))
+            .filter_map(|url| {
+                canonical
+                    .get(&canonical_form(url))
+                    .map(|entry| (url.to_string(), (*entry).clone()))
+            })
+            .collect()
     }
 
-    pub fn add(&mut self, url: String) {
-        self.urls.insert(url);
+    /// Removes entries first seen more than `days` days ago and returns how
+    /// many were dropped. Entries with an unknown `first_seen` never expire.
+    pub fn prune_older_than(&mut self, days: u64) -> usize {
+        let cutoff = Local::now() - chrono::Duration::days(days.min(i64::MAX as u64) as i64);
+        let original_count = self.urls.len();
+        self.urls.retain(|_, entry| match entry.first_seen {
+            Some(first_seen) => first_seen >= cutoff,
+            None => true,
+        });
+        let removed = original_count - self.urls.len();
+        if removed > 0 {
+            self.rebuild_keys();
+        }
+        removed
     }
 
     pub fn is_loaded(&self) -> bool {
@@ -53,20 +245,283 @@ impl MasterList {
     #[allow(dead_code)]
     pub fn clear(&mut self) {
         self.urls.clear();
+        self.keys.clear();
         self.file_path = None;
     }
 
+    /// Rewrites every entry in its canonical form (trimmed, lowercase scheme
+    /// and host, no trailing slash) and collapses entries that become equal,
+    /// keeping the earliest known `first_seen`. Returns how many were removed.
     pub fn deduplicate(&mut self) -> usize {
         let original_count = self.urls.len();
-        
-        // Create a new HashSet from our existing URLs 
-        // (HashSet automatically removes duplicates)
-        let unique_urls: HashSet<String> = self.urls.drain().collect();
-        
-        // Load the unique URLs back into self.urls
+
+        let mut unique_urls: HashMap<String, Entry> = HashMap::new();
+        for (url, entry) in self.urls.drain() {
+            let kept = unique_urls.entry(canonical_form(&url)).or_default();
+            kept.first_seen = match (kept.first_seen, entry.first_seen) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            if kept.source.is_none() {
+                kept.source = entry.source;
+            }
+        }
         self.urls = unique_urls;
-        
-        // Return how many duplicates were removed
+        self.rebuild_keys();
+
         original_count - self.urls.len()
     }
 }
+
+/// Backups of `path`, oldest first (the timestamp in the name sorts).
+fn backups_of(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let prefix = format!("{}.", path.file_name().unwrap_or_default().to_string_lossy());
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut backups: Vec<PathBuf> = fs::read_dir(dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|p| {
+            p.file_name()
+                .map(|name| name.to_string_lossy())
+                .is_some_and(|name| name.starts_with(&prefix) && name.ends_with(".bak"))
+        })
+        .collect();
+    backups.sort();
+    Ok(backups)
+}
+
+/// Reads the `(url, entry)` pairs of a list file; blank URLs are dropped.
+fn read_entries(path: &Path) -> io::Result<Vec<(String, Entry)>> {
+    let mut entries = Vec::new();
+    if is_json(path) {
+        // An empty file is a fresh list, e.g. one just created from the GUI
+        let contents = fs::read_to_string(path)?;
+        let records: Vec<Record> = if contents.trim().is_empty() {
+            Vec::new()
+        } else {
+            serde_json::from_str(&contents)?
+        };
+        for record in records {
+            entries.push((record.url.trim().to_string(), Entry {
+                first_seen: record.first_seen,
+                source: record.source,
+            }));
+        }
+    } else {
+        let reader = BufReader::new(File::open(path)?);
+        for line in reader.lines().map_while(Result::ok) {
+            let (url, first_seen) = match line.split_once('\t') {
+                Some((url, timestamp)) => (url, parse_timestamp(timestamp)),
+                None => (line.as_str(), None),
+            };
+            entries.push((url.trim().to_string(), Entry { first_seen, source: None }));
+        }
+    }
+    entries.retain(|(url, _)| !url.is_empty());
+    Ok(entries)
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+}
+
+fn parse_timestamp(timestamp: &str) -> Option<DateTime<Local>> {
+    DateTime::parse_from_rfc3339(timestamp.trim())
+        .ok()
+        .map(|t| t.with_timezone(&Local))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory under the system temp dir, unique to this test.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("csv_link_extractor_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Writes `contents` to `urls.txt` in `dir` and loads it.
+    fn list_in(dir: &Path, contents: &str, max_backups: usize) -> (MasterList, PathBuf) {
+        let path = dir.join("urls.txt");
+        fs::write(&path, contents).unwrap();
+        let mut list = MasterList::new();
+        list.set_max_backups(max_backups);
+        list.load_from_file(&path).unwrap();
+        (list, path)
+    }
+
+    /// Saves `times` times, far enough apart that each backup gets its own name.
+    fn save_repeatedly(list: &mut MasterList, times: usize) {
+        for i in 0..times {
+            list.add(format!("https://example.com/{}", i), Local::now(), None);
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            list.save().unwrap();
+        }
+    }
+
+    #[test]
+    fn save_backs_up_the_previous_list() {
+        let dir = temp_dir("master_backup");
+        let (mut list, path) = list_in(&dir, "https://one.example/\n", 5);
+        list.add(String::from("https://two.example/"), Local::now(), None);
+        list.save().unwrap();
+
+        let backups = backups_of(&path).unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(fs::read_to_string(&backups[0]).unwrap(), "https://one.example/\n");
+        assert!(fs::read_to_string(&path).unwrap().contains("https://two.example/"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn save_keeps_only_max_backups() {
+        let dir = temp_dir("master_backup_rotation");
+        let (mut list, path) = list_in(&dir, "https://one.example/\n", 2);
+        save_repeatedly(&mut list, 4);
+
+        let backups = backups_of(&path).unwrap();
+        assert_eq!(backups.len(), 2);
+        // The newest backup is the list as it was before the last save
+        assert!(fs::read_to_string(&backups[1]).unwrap().contains("https://example.com/2"));
+        assert!(!fs::read_to_string(&backups[1]).unwrap().contains("https://example.com/3"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn save_without_backups_when_max_backups_is_zero() {
+        let dir = temp_dir("master_no_backup");
+        let (mut list, path) = list_in(&dir, "https://one.example/\n", 0);
+        save_repeatedly(&mut list, 2);
+
+        assert!(backups_of(&path).unwrap().is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn restore_backup_brings_back_the_previous_list() {
+        let dir = temp_dir("master_restore");
+        let (mut list, path) = list_in(&dir, "https://one.example/\n", 5);
+        assert_eq!(list.restore_backup().unwrap(), None);
+
+        list.add(String::from("https://two.example/"), Local::now(), None);
+        list.save().unwrap();
+        let backup = backups_of(&path).unwrap().pop().unwrap();
+
+        assert_eq!(list.restore_backup().unwrap(), Some(backup));
+        assert!(list.contains("https://one.example/"));
+        assert!(!list.contains("https://two.example/"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "https://one.example/\n");
+        // The backup was used up
+        assert_eq!(list.restore_backup().unwrap(), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn json_list_keeps_first_seen_and_source() {
+        let dir = temp_dir("master_json");
+        let path = dir.join("urls.json");
+        fs::write(&path, "").unwrap();
+        let first_seen = parse_timestamp("2024-03-01T12:30:00+00:00").unwrap();
+        let mut list = MasterList::new();
+        list.set_max_backups(0);
+        list.load_from_file(&path).unwrap();
+        list.add(String::from("https://one.example/"), first_seen, Some("export.csv"));
+        list.save().unwrap();
+
+        let entries = read_entries(&path).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].0, "https://one.example/");
+        assert_eq!(entries[0].1.first_seen, Some(first_seen));
+        assert_eq!(entries[0].1.source.as_deref(), Some("export.csv"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn text_list_reloads_first_seen() {
+        let dir = temp_dir("master_text_timestamps");
+        let (list, path) = list_in(&dir, "https://one.example/\t2024-03-01T12:30:00+00:00\nhttps://two.example/\n", 0);
+        let first_seen = parse_timestamp("2024-03-01T12:30:00+00:00");
+        assert_eq!(list.first_seen("https://one.example/"), first_seen);
+        assert_eq!(list.first_seen("https://two.example/"), None);
+
+        list.save().unwrap();
+        let mut reloaded = MasterList::new();
+        reloaded.load_from_file(&path).unwrap();
+        assert_eq!(reloaded.first_seen("https://one.example/"), first_seen);
+        assert!(reloaded.contains("https://two.example/"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn empty_json_file_loads_as_an_empty_list() {
+        let dir = temp_dir("master_empty_json");
+        let path = dir.join("urls.json");
+        fs::write(&path, "").unwrap();
+        let mut list = MasterList::new();
+        list.load_from_file(&path).unwrap();
+        assert!(list.is_loaded());
+        assert!(list.urls.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn prune_older_than_drops_only_expired_entries() {
+        let dir = temp_dir("master_prune");
+        let (mut list, _) = list_in(&dir, "https://undated.example/\n", 0);
+        let now = Local::now();
+        list.add(String::from("https://old.example/"), now - chrono::Duration::days(40), None);
+        list.add(String::from("https://older.example/"), now - chrono::Duration::days(400), None);
+        list.add(String::from("https://recent.example/"), now - chrono::Duration::days(2), None);
+
+        assert_eq!(list.prune_older_than(30), 2);
+        assert!(list.contains("https://undated.example/"));
+        assert!(list.contains("https://recent.example/"));
+        assert!(!list.contains("https://old.example/"));
+        // Another spelling only matches through `keys`, so this checks they were rebuilt
+        assert!(!list.contains("HTTPS://OLD.example/"));
+        assert!(list.contains("HTTPS://RECENT.example/"));
+        assert_eq!(list.prune_older_than(30), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn merge_from_file_adds_new_urls_and_keeps_the_earlier_first_seen() {
+        let dir = temp_dir("master_merge");
+        let (mut list, path) = list_in(
+            &dir,
+            "https://shared.example/\t2024-05-01T00:00:00+00:00\nhttps://mine.example/\t2024-05-01T00:00:00+00:00\n",
+            0,
+        );
+        let other = dir.join("other.txt");
+        fs::write(
+            &other,
+            "https://shared.example/\t2024-01-01T00:00:00+00:00\nhttps://mine.example/\t2024-09-01T00:00:00+00:00\nhttps://theirs.example/\n",
+        )
+        .unwrap();
+
+        assert_eq!(list.merge_from_file(&other).unwrap(), 1);
+        assert_eq!(list.urls.len(), 3);
+        assert!(list.contains("https://theirs.example/"));
+        assert_eq!(
+            list.first_seen("https://shared.example/"),
+            parse_timestamp("2024-01-01T00:00:00+00:00")
+        );
+        assert_eq!(
+            list.first_seen("https://mine.example/"),
+            parse_timestamp("2024-05-01T00:00:00+00:00")
+        );
+        // Merging doesn't make the other file this list's file
+        assert_eq!(list.file_path.as_deref(), Some(path.to_string_lossy().as_ref()));
+        // Merging again adds nothing
+        assert_eq!(list.merge_from_file(&other).unwrap(), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,172 @@
+use crate::app_config::{LineEnding, OutputFormat, SortOrder};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+use url::Url;
+
+/// One URL as written to the output file.
+#[derive(Serialize)]
+pub struct OutputRecord<'a> {
+    pub url: &'a str,
+    pub source_file: &'a str,
+    pub domain: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_seen: Option<String>,
+    /// Times the URL was read across all files, when counting occurrences
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<usize>,
+    /// Every file the URL appeared in; when set, text output writes one
+    /// `url<TAB>file` line per source
+    #[serde(skip)]
+    pub all_sources: Option<&'a [String]>,
+}
+
+/// Sorts `urls` in place. `Domain` orders by host, then path, then the full
+/// URL; URLs that don't parse sort after the rest.
+pub fn sort_urls(urls: &mut [&String], order: SortOrder) {
+    match order {
+        SortOrder::None => {}
+        SortOrder::Alpha => urls.sort_unstable(),
+        SortOrder::Domain => urls.sort_by_cached_key(|url| {
+            let parsed = Url::parse(url).ok();
+            let host = parsed.as_ref().and_then(|u| u.host_str().map(str::to_string));
+            let path = parsed.as_ref().map(|u| u.path().to_string());
+            (host.is_none(), host, path, url.to_string())
+        }),
+    }
+}
+
+/// Writes `records` in the chosen format, ending every line with `eol`.
+///
+/// - `Txt`: one URL per line (plus tab-separated source file, `first_seen`
+///   and count when present)
+/// - `Json`: a pretty-printed array of objects (only the final newline
+///   follows `eol`; serde_json breaks its own lines with `\n`)
+/// - `Csv`: a header row (unless `header` is false, e.g. when appending)
+///   followed by `url,source_file,domain[,first_seen][,count]`
+pub fn write_records<W: Write>(
+    mut writer: W,
+    format: OutputFormat,
+    records: &[OutputRecord],
+    header: bool,
+    eol: LineEnding,
+) -> io::Result<()> {
+    let eol_str = eol.as_str();
+    match format {
+        OutputFormat::Txt => {
+            for record in records {
+                let mut suffix = record
+                    .first_seen
+                    .as_ref()
+                    .map(|first_seen| format!("\t{}", first_seen))
+                    .unwrap_or_default();
+                if let Some(count) = record.count {
+                    suffix.push_str(&format!("\t{}", count));
+                }
+                match record.all_sources {
+                    Some(sources) => {
+                        for source in sources {
+                            write!(writer, "{}\t{}{}{}", record.url, source, suffix, eol_str)?;
+                        }
+                    }
+                    None => write!(writer, "{}{}{}", record.url, suffix, eol_str)?,
+                }
+            }
+        }
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut writer, records)?;
+            writer.write_all(eol_str.as_bytes())?;
+        }
+        OutputFormat::Csv => {
+            let with_first_seen = records.first().is_some_and(|r| r.first_seen.is_some());
+            let with_count = records.first().is_some_and(|r| r.count.is_some());
+            let mut csv_writer = csv::WriterBuilder::new()
+                .has_headers(false)
+                .terminator(match eol {
+                    LineEnding::Lf => csv::Terminator::Any(b'\n'),
+                    LineEnding::Crlf => csv::Terminator::CRLF,
+                })
+                .from_writer(&mut writer);
+            let mut header_row = vec!["url", "source_file", "domain"];
+            if with_first_seen {
+                header_row.push("first_seen");
+            }
+            if with_count {
+                header_row.push("count");
+            }
+            if header {
+                csv_writer.write_record(&header_row)?;
+            }
+            for record in records {
+                let count = record.count.map(|count| count.to_string());
+                let mut row = vec![
+                    record.url,
+                    record.source_file,
+                    record.domain.as_deref().unwrap_or_default(),
+                ];
+                if with_first_seen {
+                    row.push(record.first_seen.as_deref().unwrap_or_default());
+                }
+                if with_count {
+                    row.push(count.as_deref().unwrap_or_default());
+                }
+                csv_writer.write_record(&row)?;
+            }
+            csv_writer.flush()?;
+        }
+    }
+    writer.flush()
+}
+
+/// Reads the URLs already present in an output file written by
+/// [`write_records`], so appending can skip them.
+pub fn read_output_urls<R: Read>(reader: R, format: OutputFormat) -> io::Result<HashSet<String>> {
+    match format {
+        OutputFormat::Txt => Ok(BufReader::new(reader)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| line.split('\t').next().map(|url| url.trim().to_string()))
+            .filter(|url| !url.is_empty())
+            .collect()),
+        OutputFormat::Csv => {
+            let mut rdr = csv::Reader::from_reader(reader);
+            let mut urls = HashSet::new();
+            for record in rdr.records() {
+                if let Some(url) = record?.get(0) {
+                    urls.insert(url.to_string());
+                }
+            }
+            Ok(urls)
+        }
+        OutputFormat::Json => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "appending is not supported for JSON output",
+        )),
+    }
+}
+
+/// Reads the URLs of a previous output file or a master list, picking the
+/// format from the extension: `.json` (an array of objects with a `url`),
+/// `.csv`, or text with one URL at the start of each line.
+pub fn read_url_file(path: &Path) -> io::Result<HashSet<String>> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    let file = File::open(path)?;
+    match extension.as_str() {
+        "json" => {
+            let records: Vec<serde_json::Value> = serde_json::from_reader(BufReader::new(file))?;
+            Ok(records
+                .iter()
+                .filter_map(|record| record.get("url").and_then(|url| url.as_str()))
+                .map(str::to_string)
+                .collect())
+        }
+        "csv" => read_output_urls(file, OutputFormat::Csv),
+        _ => read_output_urls(file, OutputFormat::Txt),
+    }
+}
@@ -0,0 +1,452 @@
+use crate::app_config::InputEncoding;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+use encoding_rs_io::{DecodeReaderBytes, DecodeReaderBytesBuilder};
+use bzip2::read::MultiBzDecoder;
+use flate2::read::{DeflateDecoder, MultiGzDecoder};
+use flate2::CrcReader;
+use glob::{MatchOptions, Pattern};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use xz2::read::XzDecoder;
+use zip::{CompressionMethod, ZipArchive};
+
+/// Compressed file extensions, each read through its decoder.
+pub const COMPRESSED_EXTENSIONS: [&str; 3] = ["gz", "bz2", "xz"];
+
+/// A CSV input discovered by [`collect_csv_sources`]: a file, or a file
+/// inside a `.zip` archive, either of them possibly compressed.
+///
+/// Sources are opened lazily by the worker that processes them, so walking a
+/// large tree doesn't hold thousands of file handles open at once.
+pub struct CsvSource {
+    pub name: String,
+    path: PathBuf,
+    /// The entry read from the zip archive at `path`
+    member: Option<String>,
+}
+
+impl CsvSource {
+    /// A single file outside any directory walk, named by its file name.
+    pub fn from_path(path: PathBuf) -> Self {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Self { name, path, member: None }
+    }
+
+    fn new(root: &Path, path: PathBuf) -> Self {
+        let name = path
+            .strip_prefix(root)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .into_owned();
+        Self { name, path, member: None }
+    }
+
+    /// An entry of the zip archive at `path`, named `<archive>/<entry>`.
+    fn in_archive(root: &Path, path: PathBuf, member: String) -> Self {
+        let archive = Self::new(root, path);
+        Self {
+            name: format!("{}/{}", archive.name, member),
+            path: archive.path,
+            member: Some(member),
+        }
+    }
+
+    /// Opens the source as a plain byte stream ready for `csv::Reader`.
+    pub fn open(&self) -> io::Result<Box<dyn Read + Send>> {
+        match &self.member {
+            Some(member) => Ok(decompress(Path::new(member), open_member(&self.path, member)?)),
+            None => open_input(&self.path),
+        }
+    }
+}
+
+/// Streams entry `member` of the zip archive at `path` straight from the
+/// archive file, so a large entry is never held in memory.
+///
+/// A `ZipFile` reader borrows its archive, so the entry is located through
+/// the archive and then read from its data offset with our own decoder.
+fn open_member(path: &Path, member: &str) -> io::Result<Box<dyn Read + Send>> {
+    let mut archive = ZipArchive::new(BufReader::new(File::open(path)?))?;
+    let (start, size, method, crc32) = {
+        let entry = archive.by_name(member)?;
+        (entry.data_start(), entry.compressed_size(), entry.compression(), entry.crc32())
+    };
+    let mut file = archive.into_inner();
+    file.seek(SeekFrom::Start(start))?;
+    let data = file.take(size);
+    let contents: Box<dyn Read + Send> = match method {
+        CompressionMethod::Stored => Box::new(data),
+        CompressionMethod::Deflated => Box::new(DeflateDecoder::new(data)),
+        other => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("unsupported zip compression method {:?}", other),
+            ))
+        }
+    };
+    Ok(Box::new(CheckedMember {
+        inner: CrcReader::new(contents),
+        crc32,
+    }))
+}
+
+/// Reads a zip entry and fails at the end of it when the data doesn't match
+/// the CRC recorded in the archive.
+struct CheckedMember<R> {
+    inner: CrcReader<R>,
+    crc32: u32,
+}
+
+impl<R: Read> Read for CheckedMember<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        if read == 0 && !buf.is_empty() && self.inner.crc().sum() != self.crc32 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "zip entry failed its CRC check"));
+        }
+        Ok(read)
+    }
+}
+
+fn has_extension(path: &Path, wanted: &str) -> bool {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .map(|ext| ext.eq_ignore_ascii_case(wanted))
+        .unwrap_or(false)
+}
+
+fn is_compressed(path: &Path) -> bool {
+    COMPRESSED_EXTENSIONS.iter().any(|ext| has_extension(path, ext))
+}
+
+/// The name of a compressed file without its compression extension
+/// (`export.csv.gz` -> `export.csv`), or `path` itself.
+fn inner_path(path: &Path) -> &Path {
+    if is_compressed(path) {
+        Path::new(path.file_stem().unwrap_or_default())
+    } else {
+        path
+    }
+}
+
+/// Whether `path` is tab-separated by its name (`.tsv`, or a compressed
+/// `.tsv.gz`, `.tsv.bz2` or `.tsv.xz`).
+pub fn is_tsv(path: &Path) -> bool {
+    has_extension(inner_path(path), "tsv")
+}
+
+/// Extensions (`csv`, `tsv`, `txt`) and file name globs (`export_*.csv`)
+/// choosing which files are read.
+///
+/// A `.gz`, `.bz2` or `.xz` file is read, decompressed, when the name under
+/// the compression extension has a listed extension (`export.csv.gz`, but
+/// not `backup.tar.gz`), and `.zip` archives are searched for files like these. Patterns match the file
+/// name only, ignoring case. An empty include list reads every file with a
+/// listed extension; a skip pattern always wins.
+#[derive(Clone)]
+pub struct FileFilter {
+    extensions: Vec<String>,
+    include: Vec<Pattern>,
+    skip: Vec<Pattern>,
+}
+
+impl Default for FileFilter {
+    fn default() -> Self {
+        Self {
+            extensions: vec![String::from("csv")],
+            include: Vec::new(),
+            skip: Vec::new(),
+        }
+    }
+}
+
+impl FileFilter {
+    /// An empty `extensions` list keeps the default of `.csv` only.
+    pub fn new(extensions: &[String], include: &[String], skip: &[String]) -> Result<Self, String> {
+        fn compile(patterns: &[String]) -> Result<Vec<Pattern>, String> {
+            patterns
+                .iter()
+                .map(|p| p.trim())
+                .filter(|p| !p.is_empty())
+                .map(|p| Pattern::new(p).map_err(|e| format!("invalid file pattern `{}`: {}", p, e)))
+                .collect()
+        }
+        let extensions: Vec<String> = extensions
+            .iter()
+            .map(|ext| ext.trim().trim_start_matches('.').to_ascii_lowercase())
+            .filter(|ext| !ext.is_empty())
+            .collect();
+        Ok(Self {
+            extensions: if extensions.is_empty() { Self::default().extensions } else { extensions },
+            include: compile(include)?,
+            skip: compile(skip)?,
+        })
+    }
+
+    /// Whether every file with a listed extension is read.
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.skip.is_empty()
+    }
+
+    /// The extensions read, lowercased and without the leading dot.
+    pub fn extensions(&self) -> &[String] {
+        &self.extensions
+    }
+
+    fn reads_extension(&self, path: &Path) -> bool {
+        let inner = inner_path(path);
+        self.extensions.iter().any(|ext| has_extension(inner, ext))
+    }
+
+    fn allows(&self, path: &Path) -> bool {
+        let name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(name) => name,
+            None => return self.include.is_empty(),
+        };
+        let options = MatchOptions {
+            case_sensitive: false,
+            ..MatchOptions::new()
+        };
+        let matches = |p: &Pattern| p.matches_with(name, options);
+        (self.include.is_empty() || self.include.iter().any(matches)) && !self.skip.iter().any(matches)
+    }
+}
+
+/// Opens `path` for reading, transparently decompressing `.gz`, `.bz2` and
+/// `.xz` files.
+pub fn open_input(path: &Path) -> io::Result<Box<dyn Read + Send>> {
+    Ok(decompress(path, File::open(path)?))
+}
+
+/// Wraps `input` in the decoder its name calls for; multi-member streams
+/// (several files concatenated) are read to the end.
+fn decompress<R: Read + Send + 'static>(name: &Path, input: R) -> Box<dyn Read + Send> {
+    if has_extension(name, "gz") {
+        Box::new(MultiGzDecoder::new(BufReader::new(input)))
+    } else if has_extension(name, "bz2") {
+        Box::new(MultiBzDecoder::new(BufReader::new(input)))
+    } else if has_extension(name, "xz") {
+        Box::new(XzDecoder::new_multi_decoder(BufReader::new(input)))
+    } else {
+        Box::new(input)
+    }
+}
+
+/// The entries of the zip archive at `path` that `filter` allows, by name.
+fn archive_members(path: &Path, filter: &FileFilter) -> io::Result<Vec<String>> {
+    let archive = ZipArchive::new(BufReader::new(File::open(path)?))?;
+    Ok(archive
+        .file_names()
+        .filter(|name| !name.ends_with('/'))
+        .filter(|name| {
+            let entry = Path::new(name);
+            filter.reads_extension(entry) && filter.allows(entry)
+        })
+        .map(str::to_string)
+        .collect())
+}
+
+/// Walks `root` and returns every CSV source `filter` allows, in a stable order.
+///
+/// With `recursive`, subdirectories (including symlinked ones) are descended
+/// into as well; each directory is visited once by its canonical path so
+/// symlink loops terminate. `.zip` archives are always searched, without
+/// descending into archives nested inside them. Only a failure to read
+/// `root` itself is an error; unreadable subdirectories and archives are
+/// reported and skipped.
+pub fn collect_csv_sources(root: &Path, recursive: bool, filter: &FileFilter) -> io::Result<Vec<CsvSource>> {
+    let mut sources = Vec::new();
+    let mut visited = HashSet::new();
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        if let Ok(canonical) = fs::canonicalize(&dir) {
+            if !visited.insert(canonical) {
+                continue;
+            }
+        }
+
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if dir == root => return Err(e),
+            Err(e) => {
+                log::warn!("Error reading directory {:?}: {}", dir, e);
+                continue;
+            }
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            // `Path::is_dir` follows symlinks; loops are caught by `visited`
+            let is_dir = path.is_dir();
+            if is_dir {
+                if recursive {
+                    pending.push(path);
+                }
+            } else if has_extension(&path, "zip") {
+                match archive_members(&path, filter) {
+                    Ok(members) => {
+                        sources.extend(members.into_iter().map(|member| CsvSource::in_archive(root, path.clone(), member)))
+                    }
+                    Err(e) => log::warn!("Error reading archive {:?}: {}", path, e),
+                }
+            } else if filter.reads_extension(&path) && filter.allows(&path) {
+                sources.push(CsvSource::new(root, path));
+            }
+        }
+    }
+
+    sources.sort_by(|a, b| a.path.cmp(&b.path).then_with(|| a.member.cmp(&b.member)));
+    Ok(sources)
+}
+
+/// Guesses the field delimiter from the first line of `sample`, picking the
+/// most frequent of `,`, `;`, tab and `|` outside quoted fields. Falls back to
+/// a comma when none appear.
+pub fn detect_delimiter(sample: &[u8]) -> u8 {
+    const CANDIDATES: [u8; 4] = [b',', b';', b'\t', b'|'];
+    let mut counts = [0usize; 4];
+    let mut in_quotes = false;
+
+    for &byte in sample.iter().take_while(|&&b| b != b'\n') {
+        if byte == b'"' {
+            in_quotes = !in_quotes;
+        } else if !in_quotes {
+            if let Some(i) = CANDIDATES.iter().position(|&c| c == byte) {
+                counts[i] += 1;
+            }
+        }
+    }
+
+    // Ties keep the earlier candidate, so comma wins by default
+    let mut best = 0;
+    for (i, &count) in counts.iter().enumerate().skip(1) {
+        if count > counts[best] {
+            best = i;
+        }
+    }
+    CANDIDATES[best]
+}
+
+/// Wraps `input` so it yields UTF-8 with any byte-order mark removed.
+///
+/// A BOM always wins. Without one, `Auto` peeks at the start of the stream
+/// and treats data that isn't valid UTF-8 as Windows-1252.
+pub fn decode_reader<R: Read>(input: R, encoding: InputEncoding) -> DecodeReaderBytes<BufReader<R>, Vec<u8>> {
+    let mut input = BufReader::new(input);
+    let encoding: Option<&'static Encoding> = match encoding {
+        InputEncoding::Auto => {
+            let sample = input.fill_buf().unwrap_or_default();
+            match std::str::from_utf8(sample) {
+                // An error with no length is just a character cut off by the buffer end
+                Err(e) if e.error_len().is_some() => Some(WINDOWS_1252),
+                _ => None,
+            }
+        }
+        InputEncoding::Utf8 => Some(UTF_8),
+        InputEncoding::Windows1252 => Some(WINDOWS_1252),
+        InputEncoding::Utf16Le => Some(UTF_16LE),
+        InputEncoding::Utf16Be => Some(UTF_16BE),
+    };
+    DecodeReaderBytesBuilder::new()
+        .encoding(encoding)
+        .strip_bom(true)
+        .build(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    /// A fresh directory under the system temp dir, unique to this test.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("csv_link_extractor_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn read_urls(input: impl Read) -> Vec<String> {
+        let mut rdr = csv::Reader::from_reader(input);
+        assert_eq!(rdr.headers().unwrap().iter().collect::<Vec<_>>(), ["Company", "Apply Url"]);
+        rdr.records().map(|record| record.unwrap()[1].to_string()).collect()
+    }
+
+    #[test]
+    fn open_input_reads_gzipped_csv() {
+        let dir = temp_dir("gzip");
+        let path = dir.join("export.csv.gz");
+        fs::write(&path, gzip(b"Company,Apply Url\nAcme,https://acme.example/jobs\n")).unwrap();
+
+        assert_eq!(read_urls(open_input(&path).unwrap()), ["https://acme.example/jobs"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn open_input_reads_every_gzip_member() {
+        // Concatenated gzip files, as `cat a.gz b.gz` produces
+        let dir = temp_dir("gzip_members");
+        let path = dir.join("export.csv.gz");
+        let mut data = gzip(b"Company,Apply Url\nAcme,https://acme.example/jobs\n");
+        data.extend(gzip(b"Globex,https://globex.example/careers\n"));
+        fs::write(&path, data).unwrap();
+
+        assert_eq!(
+            read_urls(open_input(&path).unwrap()),
+            ["https://acme.example/jobs", "https://globex.example/careers"]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn zip_members_are_streamed_from_the_archive() {
+        let dir = temp_dir("zip_members");
+        let mut zip = zip::ZipWriter::new(File::create(dir.join("exports.zip")).unwrap());
+        let stored = zip::write::FileOptions::default().compression_method(CompressionMethod::Stored);
+        zip.start_file("a.csv", stored).unwrap();
+        zip.write_all(b"Company,Apply Url\nAcme,https://acme.example/jobs\n").unwrap();
+        let deflated = zip::write::FileOptions::default().compression_method(CompressionMethod::Deflated);
+        zip.start_file("b.csv", deflated).unwrap();
+        zip.write_all(b"Company,Apply Url\nGlobex,https://globex.example/careers\n")
+            .unwrap();
+        zip.start_file("notes.txt", deflated).unwrap();
+        zip.write_all(b"not a csv").unwrap();
+        zip.finish().unwrap();
+
+        let sources = collect_csv_sources(&dir, false, &FileFilter::default()).unwrap();
+        let names: Vec<&str> = sources.iter().map(|source| source.name.as_str()).collect();
+        assert_eq!(names, ["exports.zip/a.csv", "exports.zip/b.csv"]);
+        assert_eq!(read_urls(sources[0].open().unwrap()), ["https://acme.example/jobs"]);
+        assert_eq!(read_urls(sources[1].open().unwrap()), ["https://globex.example/careers"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn compressed_files_are_read_by_their_inner_extension() {
+        let csv = FileFilter::default();
+        assert!(csv.reads_extension(Path::new("export.csv.gz")));
+        assert!(csv.reads_extension(Path::new("export.CSV.bz2")));
+        assert!(csv.reads_extension(Path::new("export.csv.xz")));
+        assert!(!csv.reads_extension(Path::new("backup.tar.gz")));
+        assert!(!csv.reads_extension(Path::new("data.json.gz")));
+        assert!(!csv.reads_extension(Path::new("app.log.xz")));
+        assert!(!csv.reads_extension(Path::new("export.gz")));
+
+        let tsv = FileFilter::new(&[String::from("tsv")], &[], &[]).unwrap();
+        assert!(tsv.reads_extension(Path::new("export.tsv.gz")));
+        assert!(!tsv.reads_extension(Path::new("export.csv.gz")));
+    }
+}
@@ -0,0 +1,194 @@
+use crate::app_config::{LineEnding, OutputFormat};
+use crate::exclusions::Exclusions;
+use crate::master_list::MasterList;
+use crate::output::{read_output_urls, write_records, OutputRecord};
+use crate::url_utils::{clean_host, dedup_form, dedup_key};
+use crate::{is_stdout, lock, open_output, ExtractOptions, OutputOptions, WriteSummary};
+use chrono::{DateTime, Local};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+/// Writes URLs to the output file as each CSV file finishes, instead of
+/// collecting the whole run in memory first.
+///
+/// Only a 64-bit hash of each dedup key is remembered, so memory grows by a
+/// few bytes per unique URL rather than by the URL itself. The cost: output
+/// is in discovery order, can't be live-checked or written as JSON, and a
+/// hash collision (vanishingly rare) drops a URL as a duplicate.
+pub struct StreamingWriter<'a> {
+    output: Option<Mutex<StreamOutput>>,
+    seen: Mutex<HashSet<u64>>,
+    exclusions: &'a Exclusions,
+    master_list: Mutex<&'a mut MasterList>,
+    format: OutputFormat,
+    eol: LineEnding,
+    with_source: bool,
+    first_seen: Option<String>,
+    /// Stop accepting URLs once this many have been written
+    limit: Option<usize>,
+    /// Write at most this many URLs per host, counted in `per_host`
+    max_per_domain: Option<usize>,
+    per_host: Mutex<HashMap<String, usize>>,
+    run_started: DateTime<Local>,
+    written: AtomicUsize,
+    excluded: AtomicUsize,
+    master_list_hits: AtomicUsize,
+    domain_capped: AtomicUsize,
+}
+
+struct StreamOutput {
+    writer: Box<dyn Write + Send>,
+    /// A CSV header is still owed before the first row
+    header_pending: bool,
+}
+
+fn key_hash(url: &str, options: &ExtractOptions) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    dedup_key(&dedup_form(url, options.dedup_mode), options.dedup_regex.as_ref()).hash(&mut hasher);
+    hasher.finish()
+}
+
+impl<'a> StreamingWriter<'a> {
+    /// Opens `output_path` for streaming (nothing is opened on a dry run).
+    /// When appending, URLs already in the file count as seen.
+    pub fn create(
+        output_path: &Path,
+        exclusions: &'a Exclusions,
+        master_list: &'a mut MasterList,
+        extract: &ExtractOptions,
+        options: &OutputOptions,
+    ) -> Result<Self, String> {
+        if options.format == OutputFormat::Json {
+            return Err("streaming output supports the txt and csv formats only".to_string());
+        }
+
+        let appending = options.append && !is_stdout(output_path) && fs::metadata(output_path).is_ok_and(|m| m.len() > 0);
+        let mut seen = HashSet::new();
+        if appending {
+            let existing = File::open(output_path)
+                .and_then(|file| read_output_urls(file, options.format))
+                .map_err(|e| format!("Error reading existing output file: {}", e))?;
+            seen.extend(existing.iter().map(|url| key_hash(url, extract)));
+        }
+
+        let output = if options.dry_run {
+            None
+        } else {
+            let writer =
+                open_output(output_path, options.append).map_err(|e| format!("Error creating output file: {}", e))?;
+            Some(Mutex::new(StreamOutput {
+                writer,
+                header_pending: !appending,
+            }))
+        };
+
+        let run_started = Local::now();
+        Ok(Self {
+            output,
+            seen: Mutex::new(seen),
+            exclusions,
+            master_list: Mutex::new(master_list),
+            format: options.format,
+            eol: options.eol,
+            with_source: options.with_source,
+            first_seen: options.include_first_seen.then(|| run_started.to_rfc3339()),
+            limit: extract.limit,
+            max_per_domain: options.max_per_domain,
+            per_host: Mutex::new(HashMap::new()),
+            run_started,
+            written: AtomicUsize::new(0),
+            excluded: AtomicUsize::new(0),
+            master_list_hits: AtomicUsize::new(0),
+            domain_capped: AtomicUsize::new(0),
+        })
+    }
+
+    /// Filters the URLs read from one file and writes the new ones.
+    pub fn write_file_urls(&self, source: &str, urls: Vec<String>, extract: &ExtractOptions) -> Result<(), String> {
+        let mut accepted = Vec::new();
+        {
+            let mut seen = lock(&self.seen);
+            let mut master_list = lock(&self.master_list);
+            let mut per_host = lock(&self.per_host);
+            let room = match self.limit {
+                Some(limit) => limit.saturating_sub(self.written.load(Ordering::Relaxed)),
+                None => usize::MAX,
+            };
+            for url in urls {
+                if accepted.len() >= room {
+                    break;
+                }
+                if !seen.insert(key_hash(&url, extract)) {
+                    continue;
+                }
+                if self.exclusions.is_excluded(&url) {
+                    self.excluded.fetch_add(1, Ordering::Relaxed);
+                } else if master_list.contains(&url) {
+                    self.master_list_hits.fetch_add(1, Ordering::Relaxed);
+                } else if self.max_per_domain.is_some_and(|cap| {
+                    let count = per_host.entry(clean_host(&url).unwrap_or_default()).or_insert(0);
+                    *count += 1;
+                    *count > cap
+                }) {
+                    self.domain_capped.fetch_add(1, Ordering::Relaxed);
+                } else {
+                    master_list.add(url.clone(), self.run_started, Some(source));
+                    accepted.push(url);
+                }
+            }
+            // Counted under the lock so concurrent files can't overshoot the limit
+            self.written.fetch_add(accepted.len(), Ordering::Relaxed);
+        }
+
+        let output = match &self.output {
+            Some(output) if !accepted.is_empty() => output,
+            _ => return Ok(()),
+        };
+        let sources = [source.to_string()];
+        let records: Vec<OutputRecord> = accepted
+            .iter()
+            .map(|url| OutputRecord {
+                url,
+                source_file: source,
+                domain: clean_host(url),
+                first_seen: self.first_seen.clone(),
+                count: None,
+                all_sources: self.with_source.then_some(&sources[..]),
+            })
+            .collect();
+        let mut output = lock(output);
+        let header = std::mem::take(&mut output.header_pending);
+        write_records(&mut output.writer, self.format, &records, header, self.eol)
+            .map_err(|e| format!("Error writing to file: {}", e))
+    }
+
+    /// Whether `--limit` URLs have been written, so remaining files can be skipped.
+    pub fn is_full(&self) -> bool {
+        self.limit
+            .is_some_and(|limit| self.written.load(Ordering::Relaxed) >= limit)
+    }
+
+    /// Saves the master list (unless this is a dry run) and returns the counts.
+    pub fn finish(self) -> Result<WriteSummary, String> {
+        let master_list = self.master_list.into_inner().unwrap_or_else(PoisonError::into_inner);
+        if self.output.is_some() && master_list.is_loaded() {
+            master_list
+                .save()
+                .map_err(|e| format!("Error saving master list: {}", e))?;
+        }
+        Ok(WriteSummary {
+            written: self.written.into_inner(),
+            excluded: self.excluded.into_inner(),
+            master_list_hits: self.master_list_hits.into_inner(),
+            domain_capped: self.domain_capped.into_inner(),
+            live_check: None,
+            diff: None,
+        })
+    }
+}
@@ -0,0 +1,263 @@
+use crate::app_config::DedupMode;
+use regex::Regex;
+use url::Url;
+
+/// Canonical form used to compare URLs that differ only cosmetically:
+/// lowercased scheme and host, and no trailing slash on the path.
+/// Strings that don't parse are returned trimmed.
+pub fn canonical_form(url: &str) -> String {
+    let trimmed = url.trim();
+    match Url::parse(trimmed) {
+        Ok(parsed) => parsed.as_str().trim_end_matches('/').to_string(),
+        Err(_) => trimmed.to_string(),
+    }
+}
+
+/// Whether `url` parses as an absolute URL with one of `schemes`. URLs with
+/// an authority (`https://`, `ftp://`) need a host; others (`mailto:`) need
+/// a non-empty path, so a bare `http://` or `mailto:` is rejected.
+/// Whitespace and control characters are rejected too: the parser would
+/// silently drop or percent-encode them, but the URL is written as read.
+pub fn is_valid_url(url: &str, schemes: &[String]) -> bool {
+    if url.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return false;
+    }
+    let parsed = match Url::parse(url) {
+        Ok(parsed) => parsed,
+        Err(_) => return false,
+    };
+    if !schemes.iter().any(|scheme| scheme.eq_ignore_ascii_case(parsed.scheme())) {
+        return false;
+    }
+    if parsed.cannot_be_a_base() {
+        !parsed.path().is_empty()
+    } else {
+        parsed.host_str().is_some_and(|host| !host.is_empty())
+    }
+}
+
+/// Normalizes `url` for deduplication: lowercased scheme and host, no
+/// default port, no trailing slash on the path, and no query parameters whose
+/// names match `tracking_params`. A pattern ending in `*` matches by prefix
+/// (`utm_*`), anything else must match exactly. Strings that don't parse are
+/// returned trimmed.
+pub fn normalize_url(url: &str, tracking_params: &[String]) -> String {
+    let trimmed = url.trim();
+    let mut parsed = match Url::parse(trimmed) {
+        Ok(parsed) => parsed,
+        Err(_) => return trimmed.to_string(),
+    };
+
+    if parsed.query().is_some() {
+        let kept: Vec<(String, String)> = parsed
+            .query_pairs()
+            .filter(|(name, _)| !is_tracking_param(name, tracking_params))
+            .map(|(name, value)| (name.into_owned(), value.into_owned()))
+            .collect();
+        if kept.is_empty() {
+            parsed.set_query(None);
+        } else {
+            parsed.query_pairs_mut().clear().extend_pairs(kept);
+        }
+    }
+
+    let path = parsed.path().trim_end_matches('/').to_string();
+    parsed.set_path(&path);
+    // `Url` already lowercases the scheme and host and drops default ports
+    parsed.to_string()
+}
+
+/// Removes the query string and/or fragment from `url`. Strings that don't
+/// parse are returned unchanged.
+pub fn strip_url_parts(url: &str, query: bool, fragment: bool) -> String {
+    let Ok(mut parsed) = Url::parse(url) else {
+        return url.to_string();
+    };
+    if query {
+        parsed.set_query(None);
+    }
+    if fragment {
+        parsed.set_fragment(None);
+    }
+    parsed.to_string()
+}
+
+fn is_tracking_param(name: &str, patterns: &[String]) -> bool {
+    patterns.iter().any(|pattern| match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    })
+}
+
+/// Splits a comma- or whitespace-separated list of query parameter patterns.
+pub fn parse_param_list(list: &str) -> Vec<String> {
+    list.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// The form of `url` compared when deduplicating under `mode`.
+pub fn dedup_form(url: &str, mode: DedupMode) -> String {
+    match mode {
+        DedupMode::Exact => url.to_string(),
+        // `Url` lowercases the scheme and host but leaves the path alone
+        DedupMode::CaseInsensitiveHost => Url::parse(url)
+            .map(String::from)
+            .unwrap_or_else(|_| url.to_string()),
+        DedupMode::FullyNormalized => normalize_url(url, &[]).to_lowercase(),
+    }
+}
+
+/// Returns the host of `url` with any leading `www.` removed.
+pub fn clean_host(url: &str) -> Option<String> {
+    let parsed = Url::parse(url).ok()?;
+    let host = parsed.host_str()?;
+    Some(host.strip_prefix("www.").unwrap_or(host).to_lowercase())
+}
+
+/// Include/exclude lists of domains, matched against a URL's host.
+///
+/// A domain matches its own host and any subdomain of it, so `indeed.com`
+/// covers `www.indeed.com` and `uk.indeed.com`. An empty include list allows
+/// every domain; the exclude list always wins.
+#[derive(Clone, Default)]
+pub struct DomainFilter {
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl DomainFilter {
+    pub fn new<I, E>(include: I, exclude: E) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+        E: IntoIterator,
+        E::Item: AsRef<str>,
+    {
+        fn clean(domains: impl IntoIterator<Item = impl AsRef<str>>) -> Vec<String> {
+            domains
+                .into_iter()
+                .map(|d| d.as_ref().trim().trim_start_matches("www.").to_lowercase())
+                .filter(|d| !d.is_empty())
+                .collect()
+        }
+        Self {
+            include: clean(include),
+            exclude: clean(exclude),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Whether `url` passes the filter. URLs without a host only pass when
+    /// no include list is set.
+    pub fn allows(&self, url: &str) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+        let host = match clean_host(url) {
+            Some(host) => host,
+            None => return self.include.is_empty(),
+        };
+        let matches = |domain: &String| {
+            host == *domain
+                || host
+                    .strip_suffix(domain.as_str())
+                    .is_some_and(|rest| rest.ends_with('.'))
+        };
+        if self.exclude.iter().any(matches) {
+            return false;
+        }
+        self.include.is_empty() || self.include.iter().any(matches)
+    }
+}
+
+/// Registrable domain of `url` from the public suffix list, e.g.
+/// `jobs.example.co.uk` -> `example.co.uk` and `jobs.sap.de` -> `sap.de`.
+/// IP addresses, and hosts that are themselves a public suffix, are returned
+/// as they are.
+pub fn registrable_domain(url: &str) -> Option<String> {
+    let host = clean_host(url)?;
+    if host.trim_start_matches('[').trim_end_matches(']').parse::<std::net::IpAddr>().is_ok() {
+        return Some(host);
+    }
+    Some(psl::domain_str(&host).unwrap_or(&host).to_string())
+}
+
+/// Compiles a user-supplied dedup regex. An empty pattern disables keyed
+/// dedup; otherwise the pattern must contain a named `key` capture group.
+pub fn compile_dedup_regex(pattern: &str) -> Result<Option<Regex>, String> {
+    let pattern = pattern.trim();
+    if pattern.is_empty() {
+        return Ok(None);
+    }
+    let regex = Regex::new(pattern).map_err(|e| e.to_string())?;
+    if !regex.capture_names().any(|name| name == Some("key")) {
+        return Err("pattern must contain a named capture group `(?P<key>...)`".to_string());
+    }
+    Ok(Some(regex))
+}
+
+/// Key used to decide whether two URLs are duplicates: the `key` capture of
+/// `dedup_regex` when it matches, otherwise the URL itself.
+pub fn dedup_key(url: &str, dedup_regex: Option<&Regex>) -> String {
+    dedup_regex
+        .and_then(|regex| regex.captures(url))
+        .and_then(|caps| caps.name("key"))
+        .map(|key| key.as_str().to_string())
+        .unwrap_or_else(|| url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schemes(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn is_valid_url_cases() {
+        let web = schemes(&["http", "https"]);
+        let with_mail_and_ftp = schemes(&["http", "https", "mailto", "ftp"]);
+        let cases: &[(&str, &[String], bool)] = &[
+            ("http://", &web, false),
+            ("https://", &web, false),
+            ("mailto:", &with_mail_and_ftp, false),
+            ("https://example.com/~user/it's", &web, true),
+            ("https://example.com/a b", &web, false),
+            ("https://example.com/a\tb", &web, false),
+            ("https://example.com/a\u{7}b", &web, false),
+            ("mailto:a@b.com", &web, false),
+            ("mailto:a@b.com", &with_mail_and_ftp, true),
+            ("ftp://host/x", &web, false),
+            ("ftp://host/x", &with_mail_and_ftp, true),
+            ("HTTPS://Example.com", &web, true),
+        ];
+        for (url, schemes, expected) in cases {
+            assert_eq!(is_valid_url(url, schemes), *expected, "{:?} with schemes {:?}", url, schemes);
+        }
+    }
+
+    #[test]
+    fn registrable_domain_uses_the_public_suffix_list() {
+        let cases = [
+            ("https://jobs.example.co.uk/1", "example.co.uk"),
+            ("https://www.shop.example.com.au/", "example.com.au"),
+            ("https://jobs.sap.de/apply", "sap.de"),
+            ("https://careers.ibm.de/", "ibm.de"),
+            ("https://x.abc.io/", "abc.io"),
+            ("https://a.b.example.com/", "example.com"),
+            ("https://example.com/", "example.com"),
+            ("https://192.168.1.10:8080/jobs", "192.168.1.10"),
+            ("http://[::1]/jobs", "[::1]"),
+        ];
+        for (url, expected) in cases {
+            assert_eq!(registrable_domain(url).as_deref(), Some(expected), "{}", url);
+        }
+        assert_eq!(registrable_domain("mailto:a@b.com"), None);
+    }
+}